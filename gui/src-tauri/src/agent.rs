@@ -0,0 +1,80 @@
+use tauri::AppHandle;
+use tauri::command;
+use tauri_plugin_http::reqwest;
+use std::time::Duration;
+use serde_json::Value as JsonValue;
+use url::Url;
+
+use crate::store;
+
+// The local ZTM agent's HTTP API on 127.0.0.1. The GUI keeps the port in its own
+// settings and mirrors it into the `agent-port` store key, see `set_agent_port`.
+const AGENT_PORT_KEY: &str = "agent-port";
+const DEFAULT_AGENT_PORT: u16 = 7777;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn agent_port(app: &AppHandle) -> u16 {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(AGENT_PORT_KEY))
+		.and_then(|value| value.as_u64())
+		.and_then(|port| u16::try_from(port).ok())
+		.unwrap_or(DEFAULT_AGENT_PORT)
+}
+
+// `segments` are percent-encoded as path segments, so mesh and endpoint names can
+// be passed as they are.
+pub(crate) fn agent_url(app: &AppHandle, segments: &[&str]) -> Url {
+	let mut url = Url::parse(&format!("http://127.0.0.1:{}", agent_port(app))).expect("agent URL is valid");
+	url.path_segments_mut().expect("http URL has a path").extend(segments);
+	url
+}
+
+pub(crate) async fn get(app: &AppHandle, segments: &[&str]) -> Result<reqwest::Response, String> {
+	let url = agent_url(app, segments);
+	let response = reqwest::Client::builder()
+		.timeout(REQUEST_TIMEOUT)
+		.build()
+		.map_err(|e| e.to_string())?
+		.get(url.clone())
+		.send()
+		.await
+		.map_err(|e| format!("Agent unreachable at {}: {}", url, e))?;
+	if !response.status().is_success() {
+		return Err(format!("Agent answered {} for {}", response.status(), url.path()));
+	}
+	Ok(response)
+}
+
+pub(crate) async fn get_json(app: &AppHandle, segments: &[&str]) -> Result<JsonValue, String> {
+	let body = get(app, segments).await?.bytes().await.map_err(|e| e.to_string())?;
+	serde_json::from_slice(&body).map_err(|e| e.to_string())
+}
+
+// UUID of this agent's endpoint in `mesh`.
+pub(crate) async fn local_endpoint(app: &AppHandle, mesh: &str) -> Result<String, String> {
+	let info = get_json(app, &["api", "meshes", mesh]).await?;
+	info.pointer("/agent/id")
+		.and_then(|id| id.as_str())
+		.map(|id| id.to_string())
+		.ok_or_else(|| format!("Mesh {} has no local endpoint", mesh))
+}
+
+// Address the proxy app of `mesh` listens on for this agent, e.g. `127.0.0.1:8080`;
+// None while it is not set up.
+pub(crate) async fn proxy_listen(app: &AppHandle, mesh: &str) -> Result<Option<String>, String> {
+	let endpoint = local_endpoint(app, mesh).await?;
+	let config = get_json(app, &["api", "meshes", mesh, "apps", "ztm", "proxy", "api", "endpoints", &endpoint, "config"]).await?;
+	Ok(config.get("listen").and_then(|listen| listen.as_str()).filter(|listen| !listen.is_empty()).map(|listen| listen.to_string()))
+}
+
+#[command]
+pub async fn set_agent_port(
+	app: AppHandle,
+	port: u16,
+) -> Result<(), String> {
+	let store = store::open_store(&app)?;
+	store.set(AGENT_PORT_KEY, port);
+	store::notify_store_change(&app, AGENT_PORT_KEY, "set");
+	Ok(())
+}
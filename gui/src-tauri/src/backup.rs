@@ -0,0 +1,477 @@
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use aes_gcm::{Aes256Gcm, Nonce, aead::{Aead, KeyInit}};
+use log::{info, warn};
+
+use crate::history;
+use crate::messages;
+use crate::store;
+
+// A backup is a single JSON document: a plaintext manifest describing every file
+// (name, size, sha256) and a payload holding the file contents, optionally
+// AES-256-GCM encrypted with an argon2id key derived from a passphrase.
+// The encrypted private key is part of store.json on desktop. On iOS it lives in
+// the system keychain, which a backup does not include.
+const BACKUP_FORMAT: &str = "ztm-gui-backup";
+const BACKUP_VERSION: u32 = 1;
+// Leftovers of our own restore/migration runs are never part of a backup.
+const SKIPPED_SUFFIXES: &[&str] = &[".bak", ".restore-tmp", ".pre-restore", "-wal", "-shm"];
+// sub-directory of the state directory a restore is staged in
+const STAGING_DIR: &str = "restore-staging";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackupFile {
+	pub name: String,
+	pub size: u64,
+	pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackupManifest {
+	pub app_version: String,
+	pub created_at: u64,
+	pub schema_version: u64,
+	pub files: Vec<BackupFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupEncryption {
+	kdf: String,
+	salt: String,
+	nonce: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupArchive {
+	format: String,
+	version: u32,
+	manifest: BackupManifest,
+	encryption: Option<BackupEncryption>,
+	payload: String,
+}
+
+fn state_dir(app: &AppHandle) -> Result<PathBuf, String> {
+	app.path().app_data_dir().map_err(|e| e.to_string())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+	format!("{:x}", Sha256::digest(bytes))
+}
+
+// Only top-level files are GUI state; sub-directories hold webview profiles and caches.
+fn collect_state_files(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>, String> {
+	let mut files = BTreeMap::new();
+	if !dir.exists() {
+		return Ok(files);
+	}
+	for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+		let entry = entry.map_err(|e| e.to_string())?;
+		if !entry.file_type().map_err(|e| e.to_string())?.is_file() {
+			continue;
+		}
+		let name = entry.file_name().to_string_lossy().to_string();
+		if SKIPPED_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+			continue;
+		}
+		let bytes = fs::read(entry.path()).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+		files.insert(name, bytes);
+	}
+	Ok(files)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Vec<u8>, String> {
+	argon2::hash_raw(passphrase.as_bytes(), salt, &argon2::Config::default()).map_err(|e| e.to_string())
+}
+
+fn encrypt_payload(plain: &[u8], passphrase: &str) -> Result<(Vec<u8>, BackupEncryption), String> {
+	let salt: [u8; 16] = rand::random();
+	let nonce: [u8; 12] = rand::random();
+	let key = derive_key(passphrase, &salt)?;
+	let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+	let sealed = cipher
+		.encrypt(Nonce::from_slice(&nonce), plain)
+		.map_err(|_| "Failed to encrypt backup".to_string())?;
+	Ok((sealed, BackupEncryption {
+		kdf: "argon2id".to_string(),
+		salt: BASE64.encode(salt),
+		nonce: BASE64.encode(nonce),
+	}))
+}
+
+fn decrypt_payload(sealed: &[u8], encryption: &BackupEncryption, passphrase: &str) -> Result<Vec<u8>, String> {
+	if encryption.kdf != "argon2id" {
+		return Err(format!("Unsupported key derivation: {}", encryption.kdf));
+	}
+	let salt = BASE64.decode(&encryption.salt).map_err(|e| e.to_string())?;
+	let nonce = BASE64.decode(&encryption.nonce).map_err(|e| e.to_string())?;
+	if nonce.len() != 12 {
+		return Err("Invalid backup nonce".to_string());
+	}
+	let key = derive_key(passphrase, &salt)?;
+	let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+	cipher
+		.decrypt(Nonce::from_slice(&nonce), sealed)
+		.map_err(|_| "Wrong passphrase or corrupted backup".to_string())
+}
+
+fn is_safe_name(name: &str) -> bool {
+	!name.is_empty()
+		&& name != "."
+		&& name != ".."
+		&& !name.contains('/')
+		&& !name.contains('\\')
+		&& !SKIPPED_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+// Decodes and fully checks an archive against its manifest. Nothing on disk is touched.
+fn open_archive(bytes: &[u8], passphrase: Option<&str>) -> Result<(BackupManifest, BTreeMap<String, Vec<u8>>), String> {
+	let archive: BackupArchive = serde_json::from_slice(bytes).map_err(|e| format!("Not a ZTM backup: {}", e))?;
+	if archive.format != BACKUP_FORMAT {
+		return Err(format!("Not a ZTM backup: unknown format '{}'", archive.format));
+	}
+	if archive.version > BACKUP_VERSION {
+		return Err(format!("Backup version {} is newer than supported version {}", archive.version, BACKUP_VERSION));
+	}
+	if archive.manifest.schema_version > store::current_schema_version() {
+		return Err(format!("Backup was made by a newer ZTM (store schema v{})", archive.manifest.schema_version));
+	}
+
+	let sealed = BASE64.decode(&archive.payload).map_err(|e| e.to_string())?;
+	let plain = match (&archive.encryption, passphrase) {
+		(Some(encryption), Some(passphrase)) => decrypt_payload(&sealed, encryption, passphrase)?,
+		(Some(_), None) => return Err("Backup is encrypted, a passphrase is required".to_string()),
+		(None, _) => sealed,
+	};
+	let encoded: BTreeMap<String, String> = serde_json::from_slice(&plain).map_err(|e| e.to_string())?;
+
+	let mut files = BTreeMap::new();
+	for entry in &archive.manifest.files {
+		if !is_safe_name(&entry.name) {
+			return Err(format!("Invalid file name in backup: {}", entry.name));
+		}
+		let content = encoded
+			.get(&entry.name)
+			.ok_or_else(|| format!("Backup is missing {}", entry.name))?;
+		let bytes = BASE64.decode(content).map_err(|e| e.to_string())?;
+		if bytes.len() as u64 != entry.size || sha256_hex(&bytes) != entry.sha256 {
+			return Err(format!("Checksum mismatch for {}", entry.name));
+		}
+		files.insert(entry.name.clone(), bytes);
+	}
+	if encoded.len() != files.len() {
+		return Err("Backup payload contains files not listed in the manifest".to_string());
+	}
+	Ok((archive.manifest, files))
+}
+
+// Writes the archive's files into a staging folder next to the live state, on the
+// same volume so they can be moved in with a rename.
+fn stage_files(dir: &Path, files: &BTreeMap<String, Vec<u8>>) -> Result<PathBuf, String> {
+	let staging = dir.join(STAGING_DIR);
+	if staging.exists() {
+		fs::remove_dir_all(&staging).map_err(|e| format!("Failed to clear {:?}: {}", staging, e))?;
+	}
+	fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+	for (name, bytes) in files {
+		fs::write(staging.join(name), bytes).map_err(|e| format!("Failed to stage {}: {}", name, e))?;
+	}
+	Ok(staging)
+}
+
+// Validates the staged files and migrates them to the current schema, so that
+// nothing can fail on them once they are live.
+fn prepare_staged(staging: &Path) -> Result<(), String> {
+	let store_path = staging.join(store::STORE_FILE);
+	if store_path.exists() {
+		let bytes = fs::read(&store_path).map_err(|e| e.to_string())?;
+		let mut entries: HashMap<String, JsonValue> = serde_json::from_slice(&bytes)
+			.map_err(|e| format!("{} in the backup is invalid: {}", store::STORE_FILE, e))?;
+		store::migrate_entries(&mut entries)?;
+		fs::write(&store_path, serde_json::to_vec(&entries).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+	}
+	let databases = [
+		(messages::MESSAGES_DB, messages::prepare_file as fn(&Path) -> Result<(), String>),
+		(history::HISTORY_DB, history::prepare_file),
+	];
+	for (name, prepare) in databases {
+		let path = staging.join(name);
+		if path.exists() {
+			prepare(&path).map_err(|e| format!("{} in the backup is invalid: {}", name, e))?;
+		}
+	}
+	Ok(())
+}
+
+fn previous_path(dir: &Path, name: &str) -> PathBuf {
+	dir.join(format!("{}.pre-restore", name))
+}
+
+// Puts back the live files replaced by `swap_in`; `swapped` holds each name and
+// whether a live file existed before.
+fn roll_back(dir: &Path, swapped: &[(String, bool)]) {
+	for (name, had_previous) in swapped.iter().rev() {
+		let target = dir.join(name);
+		let result = if *had_previous { fs::rename(previous_path(dir, name), &target) } else { fs::remove_file(&target) };
+		if let Err(e) = result {
+			warn!("failed to put back {:?}: {}", target, e);
+		}
+	}
+}
+
+// Moves the staged files over the live ones, keeping each replaced file as
+// `.pre-restore` until the restore is committed. Undoes itself on failure.
+fn swap_in(dir: &Path, staging: &Path, names: &[String]) -> Result<Vec<(String, bool)>, String> {
+	let mut swapped = Vec::new();
+	for name in names {
+		let (target, previous) = (dir.join(name), previous_path(dir, name));
+		let _ = fs::remove_file(&previous);
+		let had_previous = target.exists();
+		let result = (|| -> std::io::Result<()> {
+			if had_previous {
+				fs::rename(&target, &previous)?;
+			}
+			fs::rename(staging.join(name), &target)
+		})();
+		if let Err(e) = result {
+			warn!("restore of {:?} failed, rolling back: {}", target, e);
+			if previous.exists() {
+				let _ = fs::rename(&previous, &target);
+			}
+			roll_back(dir, &swapped);
+			return Err(format!("Failed to restore {:?}: {}", target, e));
+		}
+		swapped.push((name.clone(), had_previous));
+	}
+	Ok(swapped)
+}
+
+// Loads the live files again after they were swapped; store.json first since the
+// message store reads it.
+fn reopen(app: &AppHandle) -> Result<(), String> {
+	// drop the in-memory copy so the restored file wins over pending auto-saves
+	store::open_store(app)?.reload().map_err(|e| e.to_string())?;
+	store::run_migrations(app)?;
+	messages::init(app)?;
+	history::init(app)
+}
+
+// Encodes `files` as an archive, encrypted when a non-empty passphrase is given.
+fn build_archive(app_version: String, files: &BTreeMap<String, Vec<u8>>, passphrase: Option<&str>) -> Result<(BackupManifest, Vec<u8>), String> {
+	let manifest = BackupManifest {
+		app_version,
+		created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+		schema_version: store::current_schema_version(),
+		files: files
+			.iter()
+			.map(|(name, bytes)| BackupFile {
+				name: name.clone(),
+				size: bytes.len() as u64,
+				sha256: sha256_hex(bytes),
+			})
+			.collect(),
+	};
+	let encoded: BTreeMap<&String, String> = files.iter().map(|(name, bytes)| (name, BASE64.encode(bytes))).collect();
+	let plain = serde_json::to_vec(&encoded).map_err(|e| e.to_string())?;
+	let (payload, encryption) = match passphrase.filter(|p| !p.is_empty()) {
+		Some(passphrase) => {
+			let (sealed, encryption) = encrypt_payload(&plain, passphrase)?;
+			(sealed, Some(encryption))
+		}
+		None => (plain, None),
+	};
+	let archive = BackupArchive {
+		format: BACKUP_FORMAT.to_string(),
+		version: BACKUP_VERSION,
+		manifest: manifest.clone(),
+		encryption,
+		payload: BASE64.encode(payload),
+	};
+	Ok((manifest, serde_json::to_vec(&archive).map_err(|e| e.to_string())?))
+}
+
+#[command]
+pub async fn export_backup(
+	app: AppHandle,
+	path: String,
+	passphrase: Option<String>,
+) -> Result<BackupManifest, String> {
+	// make sure pending auto-saves of store.json are on disk
+	store::open_store(&app)?.save().map_err(|e| e.to_string())?;
+	messages::checkpoint()?;
+	history::checkpoint()?;
+
+	let files = collect_state_files(&state_dir(&app)?)?;
+	let (manifest, archive) = build_archive(app.package_info().version.to_string(), &files, passphrase.as_deref())?;
+	fs::write(&path, archive)
+		.map_err(|e| format!("Failed to write backup {}: {}", path, e))?;
+	info!("exported {} files to {}", manifest.files.len(), path);
+	Ok(manifest)
+}
+
+// Returns the manifest of an archive after checking it, without restoring anything.
+#[command]
+pub async fn inspect_backup(
+	path: String,
+	passphrase: Option<String>,
+) -> Result<BackupManifest, String> {
+	let bytes = fs::read(&path).map_err(|e| format!("Failed to read backup {}: {}", path, e))?;
+	let (manifest, _) = open_archive(&bytes, passphrase.as_deref())?;
+	Ok(manifest)
+}
+
+// Restores every file of the archive. The files are staged, checked and migrated
+// first; the live state only changes once that succeeded, and is put back if it
+// cannot be loaded afterwards.
+#[command]
+pub async fn import_backup(
+	app: AppHandle,
+	path: String,
+	passphrase: Option<String>,
+) -> Result<BackupManifest, String> {
+	let bytes = fs::read(&path).map_err(|e| format!("Failed to read backup {}: {}", path, e))?;
+	let (manifest, files) = open_archive(&bytes, passphrase.as_deref())?;
+	let dir = state_dir(&app)?;
+	fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	let staging = stage_files(&dir, &files)?;
+	let prepared = prepare_staged(&staging);
+	if let Err(e) = prepared {
+		let _ = fs::remove_dir_all(&staging);
+		return Err(e);
+	}
+
+	messages::checkpoint()?;
+	history::checkpoint()?;
+	messages::close();
+	history::close();
+	let names: Vec<String> = files.keys().cloned().collect();
+	let result = swap_in(&dir, &staging, &names).and_then(|swapped| match reopen(&app) {
+		Ok(()) => Ok(swapped),
+		Err(e) => {
+			warn!("restored state failed to load, rolling back: {}", e);
+			messages::close();
+			history::close();
+			roll_back(&dir, &swapped);
+			Err(e)
+		}
+	});
+	let _ = fs::remove_dir_all(&staging);
+	let swapped = match result {
+		Ok(swapped) => swapped,
+		Err(e) => {
+			if let Err(reopen_error) = reopen(&app) {
+				warn!("failed to reload the previous state: {}", reopen_error);
+			}
+			return Err(e);
+		}
+	};
+	for (name, _) in &swapped {
+		let _ = fs::remove_file(previous_path(&dir, name));
+	}
+	store::notify_store_change(&app, "", "restore");
+	info!("imported {} files from {}", manifest.files.len(), path);
+	Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sample_files() -> BTreeMap<String, Vec<u8>> {
+		BTreeMap::from([
+			("store.json".to_string(), br#"{"a":1}"#.to_vec()),
+			("messages.db".to_string(), vec![0, 1, 2, 255]),
+		])
+	}
+
+	fn tamper(archive: &[u8], f: impl FnOnce(&mut JsonValue)) -> Vec<u8> {
+		let mut value: JsonValue = serde_json::from_slice(archive).unwrap();
+		f(&mut value);
+		serde_json::to_vec(&value).unwrap()
+	}
+
+	#[test]
+	fn plain_round_trip() {
+		let (manifest, archive) = build_archive("1.0.0".to_string(), &sample_files(), None).unwrap();
+		assert_eq!(manifest.files.len(), 2);
+		let (opened, files) = open_archive(&archive, None).unwrap();
+		assert_eq!(files, sample_files());
+		assert_eq!(opened.files[0].sha256, sha256_hex(&sample_files()["messages.db"]));
+	}
+
+	#[test]
+	fn encrypted_round_trip() {
+		let (_, archive) = build_archive("1.0.0".to_string(), &sample_files(), Some("secret")).unwrap();
+		let value: JsonValue = serde_json::from_slice(&archive).unwrap();
+		assert!(value["encryption"].is_object());
+		let (_, files) = open_archive(&archive, Some("secret")).unwrap();
+		assert_eq!(files, sample_files());
+		assert!(open_archive(&archive, Some("wrong")).is_err());
+		assert!(open_archive(&archive, None).is_err());
+	}
+
+	#[test]
+	fn an_empty_passphrase_does_not_encrypt() {
+		let (_, archive) = build_archive("1.0.0".to_string(), &sample_files(), Some("")).unwrap();
+		assert_eq!(open_archive(&archive, None).unwrap().1, sample_files());
+	}
+
+	#[test]
+	fn checksum_mismatch_is_rejected() {
+		let (_, archive) = build_archive("1.0.0".to_string(), &sample_files(), None).unwrap();
+		let archive = tamper(&archive, |v| v["manifest"]["files"][0]["sha256"] = JsonValue::from("00"));
+		assert!(open_archive(&archive, None).map(|_| ()).unwrap_err().contains("Checksum mismatch"));
+	}
+
+	#[test]
+	fn unlisted_and_unsafe_files_are_rejected() {
+		let (_, archive) = build_archive("1.0.0".to_string(), &sample_files(), None).unwrap();
+		let dropped = tamper(&archive, |v| {
+			v["manifest"]["files"].as_array_mut().unwrap().pop();
+		});
+		assert!(open_archive(&dropped, None).is_err());
+		let unsafe_name = tamper(&archive, |v| v["manifest"]["files"][0]["name"] = JsonValue::from("../x"));
+		assert!(open_archive(&unsafe_name, None).map(|_| ()).unwrap_err().contains("Invalid file name"));
+	}
+
+	#[test]
+	fn staged_files_are_validated_and_migrated() {
+		let dir = std::env::temp_dir().join(format!("ztm-backup-test-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		let files = BTreeMap::from([
+			("store.json".to_string(), br#"{"history":[{"href":"http://a.example/"}]}"#.to_vec()),
+		]);
+		let staging = stage_files(&dir, &files).unwrap();
+		prepare_staged(&staging).unwrap();
+		let entries: HashMap<String, JsonValue> = serde_json::from_slice(&fs::read(staging.join("store.json")).unwrap()).unwrap();
+		assert_eq!(entries[store::SCHEMA_VERSION_KEY], JsonValue::from(store::current_schema_version()));
+
+		fs::write(staging.join(messages::MESSAGES_DB), b"not a database").unwrap();
+		assert!(prepare_staged(&staging).is_err());
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn swap_in_keeps_the_previous_files_until_committed() {
+		let dir = std::env::temp_dir().join(format!("ztm-swap-test-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("a"), b"old").unwrap();
+		let files = BTreeMap::from([("a".to_string(), b"new".to_vec()), ("b".to_string(), b"new".to_vec())]);
+		let staging = stage_files(&dir, &files).unwrap();
+		let swapped = swap_in(&dir, &staging, &["a".to_string(), "b".to_string()]).unwrap();
+		assert_eq!(fs::read(dir.join("a")).unwrap(), b"new");
+		roll_back(&dir, &swapped);
+		assert_eq!(fs::read(dir.join("a")).unwrap(), b"old");
+		assert!(!dir.join("b").exists());
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,783 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::command;
+use tauri::Webview;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use url::Url;
+use log::info;
+
+use crate::favicons;
+use crate::scope;
+use crate::store;
+use crate::tabs;
+
+// Browser bookmarks live under the single `bookmarks` key of store.json: folders
+// and bookmarks in display order, each scoped to a mesh (`""` shows in every mesh).
+// A URL is kept at most once per mesh and folder, compared after `normalize_url`.
+pub const BOOKMARKS_KEY: &str = "bookmarks";
+// well-known folder behind the app grid of the browser page
+pub const SHORTCUTS_FOLDER: &str = "shortcuts";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+	pub id: String,
+	pub url: String,
+	pub title: String,
+	#[serde(default)]
+	pub icon: String,
+	// `None` is the root folder
+	#[serde(default)]
+	pub folder: Option<String>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+	#[serde(default)]
+	pub mesh: String,
+	#[serde(default)]
+	pub created_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BookmarkFolder {
+	pub id: String,
+	pub name: String,
+	#[serde(default)]
+	pub parent: Option<String>,
+	#[serde(default)]
+	pub mesh: String,
+	#[serde(default)]
+	pub created_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkTree {
+	#[serde(default)]
+	pub next_id: u64,
+	#[serde(default)]
+	pub folders: Vec<BookmarkFolder>,
+	#[serde(default)]
+	pub bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct NewBookmark {
+	pub url: String,
+	#[serde(default)]
+	pub title: String,
+	#[serde(default)]
+	pub icon: String,
+	#[serde(default)]
+	pub folder: Option<String>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+	// defaults to the mesh of the calling browser tab
+	#[serde(default)]
+	pub mesh: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct BookmarkPatch {
+	pub url: Option<String>,
+	pub title: Option<String>,
+	pub icon: Option<String>,
+	pub tags: Option<Vec<String>>,
+}
+
+lazy_static! {
+	// serialises read-modify-write of the `bookmarks` key
+	static ref BOOKMARKS: Mutex<()> = Mutex::new(());
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Lower-cased scheme and host, no default port, no fragment and no trailing `/`
+// on paths, so `HTTP://Example.com:80/a/#top` and `http://example.com/a` are one bookmark.
+pub fn normalize_url(url: &str) -> String {
+	let Ok(mut parsed) = Url::parse(url.trim()) else {
+		return url.trim().to_string();
+	};
+	parsed.set_fragment(None);
+	let mut text = parsed.to_string();
+	if parsed.path() != "/" && parsed.query().is_none() && text.ends_with('/') {
+		text.pop();
+	}
+	text
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+	Url::parse(url.trim()).map(|_| ()).map_err(|_| format!("Invalid bookmark URL: {}", url))
+}
+
+impl BookmarkTree {
+	fn allocate_id(&mut self, prefix: &str) -> String {
+		self.next_id += 1;
+		format!("{}{}", prefix, self.next_id)
+	}
+
+	fn find_duplicate(&self, url: &str, mesh: &str, folder: &Option<String>) -> Option<usize> {
+		let normalized = normalize_url(url);
+		self.bookmarks
+			.iter()
+			.position(|b| b.mesh == mesh && &b.folder == folder && normalize_url(&b.url) == normalized)
+	}
+
+	fn ensure_shortcuts_folder(&mut self) {
+		if !self.folders.iter().any(|f| f.id == SHORTCUTS_FOLDER) {
+			self.folders.push(BookmarkFolder {
+				id: SHORTCUTS_FOLDER.to_string(),
+				name: "Shortcuts".to_string(),
+				parent: None,
+				mesh: String::new(),
+				created_at: now(),
+			});
+		}
+	}
+
+	fn has_folder(&self, folder: &Option<String>) -> bool {
+		folder.as_ref().is_none_or(|id| self.folders.iter().any(|f| &f.id == id))
+	}
+
+	// Adds `bookmark`, or merges it into the existing entry for the same URL, mesh
+	// and folder: a non-empty title or icon wins and tags are unioned.
+	fn update(&mut self, id: &str, patch: BookmarkPatch) -> Result<Bookmark, String> {
+		let index = self.bookmarks.iter().position(|b| b.id == id).ok_or_else(|| format!("No bookmark {}", id))?;
+		if let Some(url) = &patch.url {
+			check_url(url)?;
+			let bookmark = &self.bookmarks[index];
+			if self.find_duplicate(url, &bookmark.mesh, &bookmark.folder).is_some_and(|i| i != index) {
+				return Err(format!("{} is already bookmarked", url));
+			}
+		}
+		let bookmark = &mut self.bookmarks[index];
+		if let Some(url) = patch.url {
+			bookmark.url = url;
+		}
+		if let Some(title) = patch.title {
+			bookmark.title = title;
+		}
+		if let Some(icon) = patch.icon {
+			bookmark.icon = icon;
+		}
+		if let Some(tags) = patch.tags {
+			bookmark.tags = tags;
+		}
+		Ok(bookmark.clone())
+	}
+
+	fn upsert(&mut self, bookmark: NewBookmark, mesh: String) -> Bookmark {
+		if let Some(index) = self.find_duplicate(&bookmark.url, &mesh, &bookmark.folder) {
+			let existing = &mut self.bookmarks[index];
+			if !bookmark.title.is_empty() {
+				existing.title = bookmark.title;
+			}
+			if !bookmark.icon.is_empty() {
+				existing.icon = bookmark.icon;
+			}
+			for tag in bookmark.tags {
+				if !existing.tags.contains(&tag) {
+					existing.tags.push(tag);
+				}
+			}
+			return existing.clone();
+		}
+		let id = self.allocate_id("b");
+		let created = Bookmark {
+			id,
+			title: if bookmark.title.is_empty() { bookmark.url.clone() } else { bookmark.title },
+			url: bookmark.url,
+			icon: bookmark.icon,
+			folder: bookmark.folder,
+			tags: bookmark.tags,
+			mesh,
+			created_at: now(),
+		};
+		self.bookmarks.push(created.clone());
+		created
+	}
+
+	// Adds parsed Netscape `entries` below `folder`, reusing folders of the same name.
+	fn import(&mut self, entries: Vec<(Vec<String>, NewBookmark)>, mesh: &str, folder: &Option<String>) -> Result<usize, String> {
+		if !self.has_folder(folder) {
+			return Err(format!("No bookmark folder {}", folder.as_deref().unwrap_or("")));
+		}
+		let count = entries.len();
+		let mut folder_ids: HashMap<Vec<String>, Option<String>> = HashMap::new();
+		folder_ids.insert(vec![], folder.clone());
+		for (path, mut bookmark) in entries {
+			for depth in 1..=path.len() {
+				let key = path[..depth].to_vec();
+				if folder_ids.contains_key(&key) {
+					continue;
+				}
+				let parent = folder_ids[&path[..depth - 1].to_vec()].clone();
+				let name = key[depth - 1].clone();
+				let existing = self
+					.folders
+					.iter()
+					.find(|f| f.parent == parent && f.name == name && f.mesh == mesh)
+					.map(|f| f.id.clone());
+				let id = match existing {
+					Some(id) => id,
+					None => {
+						let id = self.allocate_id("f");
+						self.folders.push(BookmarkFolder { id: id.clone(), name, parent, mesh: mesh.to_string(), created_at: now() });
+						id
+					}
+				};
+				folder_ids.insert(key, Some(id));
+			}
+			bookmark.folder = folder_ids[&path].clone();
+			self.upsert(bookmark, mesh.to_string());
+		}
+		Ok(count)
+	}
+
+	// Folder `id` and every folder below it.
+	fn subtree(&self, id: &str) -> Vec<String> {
+		let mut ids = vec![id.to_string()];
+		let mut i = 0;
+		while i < ids.len() {
+			let parent = ids[i].clone();
+			ids.extend(self.folders.iter().filter(|f| f.parent.as_deref() == Some(parent.as_str())).map(|f| f.id.clone()));
+			i += 1;
+		}
+		ids
+	}
+}
+
+fn load_tree(app: &AppHandle) -> Result<BookmarkTree, String> {
+	let store = store::open_store(app)?;
+	Ok(store
+		.get(BOOKMARKS_KEY)
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default())
+}
+
+// Runs `f` on the stored tree and writes it back if `f` succeeds.
+fn update_tree<T>(app: &AppHandle, op: &'static str, f: impl FnOnce(&mut BookmarkTree) -> Result<T, String>) -> Result<T, String> {
+	let _guard = BOOKMARKS.lock().unwrap();
+	let mut tree = load_tree(app)?;
+	let result = f(&mut tree)?;
+	let store = store::open_store(app)?;
+	store.set(BOOKMARKS_KEY, serde_json::to_value(&tree).map_err(|e| e.to_string())?);
+	store::notify_store_change(app, BOOKMARKS_KEY, op);
+	Ok(result)
+}
+
+// Points bookmark `id` at `icon`, e.g. its cached copy.
+pub(crate) fn set_icon(app: &AppHandle, id: &str, icon: &str) -> Result<(), String> {
+	update_tree(app, "update", |tree| {
+		if let Some(bookmark) = tree.bookmarks.iter_mut().find(|b| b.id == id) {
+			bookmark.icon = icon.to_string();
+		}
+		Ok(())
+	})
+}
+
+// Icons of every bookmark, so the favicon cache keeps what is still in use.
+pub(crate) fn icons(app: &AppHandle) -> Vec<String> {
+	load_tree(app)
+		.map(|tree| tree.bookmarks.into_iter().map(|b| b.icon).collect())
+		.unwrap_or_default()
+}
+
+fn caller_mesh(webview: &Webview) -> String {
+	tabs::find_tab(webview.label()).map(|t| t.mesh).unwrap_or_default()
+}
+
+// Pages in browser webviews only ever see their own tab's mesh, whatever they ask for.
+pub(crate) fn requested_mesh(webview: &Webview, mesh: Option<String>) -> Option<String> {
+	if scope::is_browser_webview(webview.label()) {
+		return Some(caller_mesh(webview));
+	}
+	mesh
+}
+
+fn visible_in(mesh: &Option<String>, item_mesh: &str) -> bool {
+	item_mesh.is_empty() || mesh.as_ref().is_none_or(|m| m == item_mesh)
+}
+
+// store.json v2: the `history` (starred pages) and `shortcut` lists become root and
+// shortcut-folder bookmarks.
+pub(crate) fn migrate_legacy_lists(data: &mut HashMap<String, JsonValue>) -> Result<(), String> {
+	let mut tree: BookmarkTree = match data.get(BOOKMARKS_KEY) {
+		Some(value) => serde_json::from_value(value.clone()).map_err(|e| e.to_string())?,
+		None => BookmarkTree::default(),
+	};
+	tree.ensure_shortcuts_folder();
+	for (key, folder) in [("history", None), ("shortcut", Some(SHORTCUTS_FOLDER.to_string()))] {
+		let Some(items) = data.remove(key) else {
+			continue;
+		};
+		for item in items.as_array().cloned().unwrap_or_default() {
+			let text = |field: &str| item.get(field).and_then(|v| v.as_str()).unwrap_or("").to_string();
+			if text("href").is_empty() {
+				continue;
+			}
+			tree.upsert(NewBookmark {
+				url: text("href"),
+				title: text("title"),
+				icon: text("icon"),
+				folder: folder.clone(),
+				tags: vec![],
+				mesh: None,
+			}, String::new());
+		}
+	}
+	data.insert(BOOKMARKS_KEY.to_string(), serde_json::to_value(&tree).map_err(|e| e.to_string())?);
+	Ok(())
+}
+
+// Folders and bookmarks visible in `mesh` (all meshes when omitted), in display order.
+#[command]
+pub async fn get_bookmark_tree(
+	app: AppHandle,
+	mesh: Option<String>,
+) -> Result<BookmarkTree, String> {
+	let mut tree = load_tree(&app)?;
+	tree.folders.retain(|f| visible_in(&mesh, &f.mesh));
+	tree.bookmarks.retain(|b| visible_in(&mesh, &b.mesh));
+	Ok(tree)
+}
+
+// `folder: ""` lists the root folder only; without `folder` every folder is searched.
+// Without `mesh`, a browser tab sees the bookmarks of its own mesh.
+#[command]
+pub async fn list_bookmarks(
+	app: AppHandle,
+	webview: Webview,
+	mesh: Option<String>,
+	folder: Option<String>,
+	tag: Option<String>,
+	query: Option<String>,
+) -> Result<Vec<Bookmark>, String> {
+	let mesh = requested_mesh(&webview, mesh).or_else(|| Some(caller_mesh(&webview)).filter(|m| !m.is_empty()));
+	let query = query.map(|q| q.to_lowercase());
+	Ok(load_tree(&app)?
+		.bookmarks
+		.into_iter()
+		.filter(|b| visible_in(&mesh, &b.mesh))
+		.filter(|b| match folder.as_deref() {
+			None => true,
+			Some("") => b.folder.is_none(),
+			Some(id) => b.folder.as_deref() == Some(id),
+		})
+		.filter(|b| tag.as_ref().is_none_or(|t| b.tags.contains(t)))
+		.filter(|b| query.as_ref().is_none_or(|q| b.title.to_lowercase().contains(q) || b.url.to_lowercase().contains(q)))
+		.collect())
+}
+
+// The bookmark for `url` in `folder` (root when omitted), if any.
+pub(crate) fn find_for(app: &AppHandle, webview: &Webview, url: &str, mesh: Option<String>, folder: Option<String>) -> Result<Option<Bookmark>, String> {
+	let mesh = requested_mesh(webview, mesh).unwrap_or_else(|| caller_mesh(webview));
+	let tree = load_tree(app)?;
+	let normalized = normalize_url(url);
+	Ok(tree
+		.bookmarks
+		.into_iter()
+		.find(|b| visible_in(&Some(mesh.clone()), &b.mesh) && b.folder == folder && normalize_url(&b.url) == normalized))
+}
+
+// Adds a bookmark on behalf of `webview`: its mesh is the default scope and its
+// proxy is used to fetch the icon.
+pub(crate) fn add_for(app: &AppHandle, webview: &Webview, bookmark: NewBookmark) -> Result<Bookmark, String> {
+	check_url(&bookmark.url)?;
+	let mesh = requested_mesh(webview, bookmark.mesh.clone()).unwrap_or_else(|| caller_mesh(webview));
+	let proxy = tabs::find_tab(webview.label())
+		.map(|t| t.proxy)
+		.filter(|p| !p.is_empty())
+		.or_else(|| tabs::mesh_proxy(&mesh))
+		.unwrap_or_default();
+	let created = update_tree(app, "add", |tree| {
+		if bookmark.folder.as_deref() == Some(SHORTCUTS_FOLDER) {
+			tree.ensure_shortcuts_folder();
+		}
+		if !tree.has_folder(&bookmark.folder) {
+			return Err(format!("No bookmark folder {}", bookmark.folder.as_deref().unwrap_or("")));
+		}
+		Ok(tree.upsert(bookmark, mesh))
+	})?;
+	favicons::cache_bookmark_icon(app, &created, proxy, tabs::is_private(webview.label()));
+	Ok(created)
+}
+
+pub(crate) fn remove(app: &AppHandle, id: &str) -> Result<(), String> {
+	update_tree(app, "remove", |tree| {
+		tree.bookmarks.retain(|b| b.id != id);
+		Ok(())
+	})
+}
+
+#[command]
+pub async fn find_bookmark(
+	app: AppHandle,
+	webview: Webview,
+	url: String,
+	mesh: Option<String>,
+	folder: Option<String>,
+) -> Result<Option<Bookmark>, String> {
+	find_for(&app, &webview, &url, mesh, folder)
+}
+
+#[command]
+pub async fn add_bookmark(
+	app: AppHandle,
+	webview: Webview,
+	bookmark: NewBookmark,
+) -> Result<Bookmark, String> {
+	add_for(&app, &webview, bookmark)
+}
+
+#[command]
+pub async fn update_bookmark(
+	app: AppHandle,
+	id: String,
+	patch: BookmarkPatch,
+) -> Result<Bookmark, String> {
+	update_tree(&app, "update", |tree| tree.update(&id, patch))
+}
+
+#[command]
+pub async fn remove_bookmark(
+	app: AppHandle,
+	id: String,
+) -> Result<(), String> {
+	remove(&app, &id)
+}
+
+// Moves a bookmark into `folder` (root when omitted) at `index` among that folder's bookmarks.
+#[command]
+pub async fn move_bookmark(
+	app: AppHandle,
+	id: String,
+	folder: Option<String>,
+	index: usize,
+) -> Result<(), String> {
+	update_tree(&app, "move", |tree| {
+		if !tree.has_folder(&folder) {
+			return Err(format!("No bookmark folder {}", folder.as_deref().unwrap_or("")));
+		}
+		let from = tree.bookmarks.iter().position(|b| b.id == id).ok_or_else(|| format!("No bookmark {}", id))?;
+		let mut bookmark = tree.bookmarks.remove(from);
+		if tree.find_duplicate(&bookmark.url, &bookmark.mesh, &folder).is_some() {
+			return Err(format!("{} is already in that folder", bookmark.url));
+		}
+		bookmark.folder = folder;
+		// translate the index among siblings into a position in the flat list
+		let to = tree
+			.bookmarks
+			.iter()
+			.enumerate()
+			.filter(|(_, b)| b.folder == bookmark.folder)
+			.map(|(i, _)| i)
+			.nth(index)
+			.unwrap_or(tree.bookmarks.len());
+		tree.bookmarks.insert(to, bookmark);
+		Ok(())
+	})
+}
+
+#[command]
+pub async fn create_bookmark_folder(
+	app: AppHandle,
+	name: String,
+	parent: Option<String>,
+	mesh: Option<String>,
+) -> Result<BookmarkFolder, String> {
+	update_tree(&app, "add", |tree| {
+		if !tree.has_folder(&parent) {
+			return Err(format!("No bookmark folder {}", parent.as_deref().unwrap_or("")));
+		}
+		let folder = BookmarkFolder {
+			id: tree.allocate_id("f"),
+			name,
+			parent,
+			mesh: mesh.unwrap_or_default(),
+			created_at: now(),
+		};
+		tree.folders.push(folder.clone());
+		Ok(folder)
+	})
+}
+
+#[command]
+pub async fn rename_bookmark_folder(
+	app: AppHandle,
+	id: String,
+	name: String,
+) -> Result<(), String> {
+	update_tree(&app, "update", |tree| {
+		let folder = tree.folders.iter_mut().find(|f| f.id == id).ok_or_else(|| format!("No bookmark folder {}", id))?;
+		folder.name = name;
+		Ok(())
+	})
+}
+
+// Deletes a folder with its sub-folders and their bookmarks.
+#[command]
+pub async fn remove_bookmark_folder(
+	app: AppHandle,
+	id: String,
+) -> Result<(), String> {
+	update_tree(&app, "remove", |tree| {
+		let ids = tree.subtree(&id);
+		tree.folders.retain(|f| !ids.contains(&f.id));
+		tree.bookmarks.retain(|b| b.folder.as_ref().is_none_or(|f| !ids.contains(f)));
+		Ok(())
+	})
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape_html(text: &str) -> String {
+	text
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&amp;", "&")
+}
+
+// Value of `name="..."` inside a start tag, case-insensitively.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+	let lower = tag.to_ascii_lowercase();
+	let needle = format!("{}=\"", name);
+	let start = lower.find(&needle)? + needle.len();
+	let end = tag[start..].find('"')? + start;
+	Some(unescape_html(&tag[start..end]))
+}
+
+// Text between the end of the start tag at `from` and the next `</closing`.
+fn element_text<'a>(html: &'a str, lower: &str, from: usize, closing: &str) -> Option<(&'a str, usize)> {
+	let open_end = html[from..].find('>')? + from + 1;
+	let close = lower[open_end..].find(closing)? + open_end;
+	Some((&html[open_end..close], close))
+}
+
+// Reads the Netscape bookmark file format written by every major browser: nested
+// `<DL>` lists, `<H3>` folder titles and `<A HREF>` entries.
+fn parse_netscape(html: &str) -> Vec<(Vec<String>, NewBookmark)> {
+	let lower = html.to_ascii_lowercase();
+	let mut path: Vec<String> = vec![];
+	let mut pending_folder: Option<String> = None;
+	let mut entries = vec![];
+	let mut depth = 0usize;
+	let mut pos = 0;
+	while let Some(offset) = lower[pos..].find('<') {
+		let at = pos + offset;
+		let rest = &lower[at..];
+		if rest.starts_with("<h3") {
+			if let Some((text, end)) = element_text(html, &lower, at, "</h3") {
+				pending_folder = Some(unescape_html(text.trim()));
+				pos = end;
+				continue;
+			}
+		} else if rest.starts_with("<dl") {
+			depth += 1;
+			// the outermost list is the file itself, not a folder
+			if depth > 1 {
+				path.push(pending_folder.take().unwrap_or_default());
+			}
+		} else if rest.starts_with("</dl") {
+			if depth > 1 {
+				path.pop();
+			}
+			depth = depth.saturating_sub(1);
+		} else if rest.starts_with("<a ") {
+			let tag_end = lower[at..].find('>').map(|i| at + i).unwrap_or(lower.len());
+			let tag = &html[at..tag_end];
+			if let (Some(url), Some((text, end))) = (attribute(tag, "href"), element_text(html, &lower, at, "</a")) {
+				let tags = attribute(tag, "tags")
+					.map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+					.unwrap_or_default();
+				entries.push((path.clone(), NewBookmark {
+					url,
+					title: unescape_html(text.trim()),
+					icon: attribute(tag, "icon").unwrap_or_default(),
+					folder: None,
+					tags,
+					mesh: None,
+				}));
+				pos = end;
+				continue;
+			}
+		}
+		pos = at + 1;
+	}
+	// `place:` queries, `javascript:` bookmarklets and the like have no meaning here
+	entries.retain(|(_, b)| Url::parse(&b.url).is_ok_and(|u| u.scheme() == "http" || u.scheme() == "https"));
+	entries
+}
+
+// Imports a Netscape bookmark file into `folder` (root when omitted) of `mesh`,
+// recreating its folder structure. Returns the number of bookmarks added or merged.
+#[command]
+pub async fn import_bookmarks(
+	app: AppHandle,
+	path: String,
+	mesh: Option<String>,
+	folder: Option<String>,
+) -> Result<usize, String> {
+	let html = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+	let entries = parse_netscape(&html);
+	let mesh = mesh.unwrap_or_default();
+	let count = update_tree(&app, "import", |tree| tree.import(entries, &mesh, &folder))?;
+	info!("imported {} bookmarks from {}", count, path);
+	Ok(count)
+}
+
+// Writes the bookmarks visible in `mesh` (all when omitted) as a Netscape bookmark file.
+#[command]
+pub async fn export_bookmarks(
+	app: AppHandle,
+	path: String,
+	mesh: Option<String>,
+) -> Result<usize, String> {
+	let tree = load_tree(&app)?;
+	let (out, count) = write_netscape(&tree, &mesh);
+	fs::write(&path, out).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+	info!("exported {} bookmarks to {}", count, path);
+	Ok(count)
+}
+
+// The bookmarks of `tree` visible in `mesh` as a Netscape bookmark file, and their number.
+fn write_netscape(tree: &BookmarkTree, mesh: &Option<String>) -> (String, usize) {
+	fn write_folder(tree: &BookmarkTree, mesh: &Option<String>, parent: Option<&str>, indent: usize, out: &mut String) -> usize {
+		let pad = "    ".repeat(indent);
+		let mut count = 0;
+		for folder in tree.folders.iter().filter(|f| f.parent.as_deref() == parent && visible_in(mesh, &f.mesh)) {
+			out.push_str(&format!("{}<DT><H3 ADD_DATE=\"{}\">{}</H3>\n{}<DL><p>\n", pad, folder.created_at, escape_html(&folder.name), pad));
+			count += write_folder(tree, mesh, Some(&folder.id), indent + 1, out);
+			out.push_str(&format!("{}</DL><p>\n", pad));
+		}
+		for bookmark in tree.bookmarks.iter().filter(|b| b.folder.as_deref() == parent && visible_in(mesh, &b.mesh)) {
+			out.push_str(&format!("{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"", pad, escape_html(&bookmark.url), bookmark.created_at));
+			if !bookmark.icon.is_empty() {
+				out.push_str(&format!(" ICON=\"{}\"", escape_html(&bookmark.icon)));
+			}
+			if !bookmark.tags.is_empty() {
+				out.push_str(&format!(" TAGS=\"{}\"", escape_html(&bookmark.tags.join(","))));
+			}
+			out.push_str(&format!(">{}</A>\n", escape_html(&bookmark.title)));
+			count += 1;
+		}
+		count
+	}
+	let mut out = String::from(
+		"<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+		<!-- This is an automatically generated file.\n     It will be read and overwritten.\n     DO NOT EDIT! -->\n\
+		<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+		<TITLE>Bookmarks</TITLE>\n<H1>Bookmarks</H1>\n<DL><p>\n",
+	);
+	let count = write_folder(tree, mesh, None, 1, &mut out);
+	out.push_str("</DL><p>\n");
+	(out, count)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn bookmark(url: &str, title: &str) -> NewBookmark {
+		NewBookmark { url: url.to_string(), title: title.to_string(), icon: String::new(), folder: None, tags: vec![], mesh: None }
+	}
+
+	fn sample() -> BookmarkTree {
+		let mut tree = BookmarkTree::default();
+		let entries = vec![
+			(vec![], bookmark("https://example.com/", "Example & <Co>")),
+			(vec!["Work".to_string()], bookmark("https://work.example/wiki?a=1&b=\"2\"", "Wiki")),
+			(vec!["Work".to_string(), "Tools".to_string()], NewBookmark {
+				icon: "data:image/png;base64,AAAA".to_string(),
+				tags: vec!["dev".to_string(), "ci".to_string()],
+				..bookmark("https://ci.example/", "CI")
+			}),
+		];
+		tree.import(entries, "", &None).unwrap();
+		tree
+	}
+
+	// folder path, url, title, icon and tags
+	type Flat = (Vec<String>, String, String, String, Vec<String>);
+
+	fn flatten(tree: &BookmarkTree) -> Vec<Flat> {
+		let folder_path = |mut id: Option<String>| {
+			let mut path = vec![];
+			while let Some(folder) = id.and_then(|id| tree.folders.iter().find(|f| f.id == id)) {
+				path.insert(0, folder.name.clone());
+				id = folder.parent.clone();
+			}
+			path
+		};
+		tree.bookmarks
+			.iter()
+			.map(|b| (folder_path(b.folder.clone()), b.url.clone(), b.title.clone(), b.icon.clone(), b.tags.clone()))
+			.collect()
+	}
+
+	#[test]
+	fn export_then_import_keeps_everything() {
+		let tree = sample();
+		let (html, count) = write_netscape(&tree, &None);
+		assert_eq!(count, 3);
+		let mut imported = BookmarkTree::default();
+		assert_eq!(imported.import(parse_netscape(&html), "", &None), Ok(3));
+		let mut expected = flatten(&tree);
+		let mut actual = flatten(&imported);
+		expected.sort();
+		actual.sort();
+		assert_eq!(actual, expected);
+	}
+
+	#[test]
+	fn importing_twice_merges() {
+		let mut tree = sample();
+		let (html, _) = write_netscape(&tree, &None);
+		tree.import(parse_netscape(&html), "", &None).unwrap();
+		assert_eq!(tree.bookmarks.len(), 3);
+		assert_eq!(tree.folders.len(), 2);
+	}
+
+	#[test]
+	fn export_is_scoped_to_the_mesh() {
+		let mut tree = sample();
+		tree.import(vec![(vec![], bookmark("https://mesh.example/", "Mesh only"))], "m1", &None).unwrap();
+		assert_eq!(write_netscape(&tree, &Some("m1".to_string())).1, 4);
+		assert_eq!(write_netscape(&tree, &Some("m2".to_string())).1, 3);
+	}
+
+	#[test]
+	fn unsupported_links_are_skipped() {
+		let html = "<DL><p>\n<DT><A HREF=\"javascript:alert(1)\">x</A>\n<DT><A HREF=\"place:sort=8\">y</A>\n\
+			<DT><a href=\"http://ok.example/\" tags=\" a , ,b\">ok</a>\n</DL>";
+		let entries = parse_netscape(html);
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].1.tags, vec!["a".to_string(), "b".to_string()]);
+	}
+
+	#[test]
+	fn import_needs_an_existing_folder() {
+		let mut tree = BookmarkTree::default();
+		assert!(tree.import(vec![], "", &Some("f9".to_string())).is_err());
+	}
+
+	#[test]
+	fn update_validates_the_url() {
+		let mut tree = sample();
+		let id = tree.bookmarks[0].id.clone();
+		let patch = |url: &str| BookmarkPatch { url: Some(url.to_string()), ..Default::default() };
+		assert!(tree.update(&id, patch("not a url")).is_err());
+		assert!(tree.update(&id, patch("https://ci.example")).is_ok_and(|b| b.url == "https://ci.example"));
+		assert_eq!(tree.bookmarks[0].url, "https://ci.example");
+		assert!(tree.update("missing", patch("https://other.example/")).is_err());
+	}
+}
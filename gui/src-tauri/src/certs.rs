@@ -0,0 +1,425 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::Webview;
+use tauri::command;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+use log::{info, warn};
+
+use crate::agent;
+use crate::navigation;
+use crate::store;
+use crate::tabs;
+
+// Certificates the browser webviews accept despite TLS errors: per-host exceptions
+// pinned to a SHA-256 fingerprint, kept in the `cert-exceptions` store key (private
+// tabs only keep theirs until the app quits), and, per mesh, certificates issued by
+// the mesh CA (`mesh-ca-trust-{mesh}`) for hosts reached through the mesh proxy.
+// Anything else gets an interstitial whose buttons link to `ztm-cert://{action}/{token}`,
+// caught by `on_navigation`; the token is random so the page cannot forge the link. Only WebKitGTK reports TLS errors to the app.
+const EXCEPTIONS_KEY: &str = "cert-exceptions";
+const ACTION_SCHEME: &str = "ztm-cert";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CertException {
+	pub host: String,
+	// SHA-256 of the DER certificate, `AB:CD:...`
+	pub fingerprint: String,
+	// unix seconds
+	#[serde(default)]
+	pub added: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MeshCa {
+	pub pem: String,
+	pub fingerprint: String,
+}
+
+// A certificate error shown in an interstitial.
+#[derive(Clone)]
+struct CertProblem {
+	label: String,
+	url: String,
+	host: String,
+	fingerprint: String,
+}
+
+lazy_static! {
+	// token -> problem shown in an interstitial
+	static ref PROBLEMS: Mutex<HashMap<String, CertProblem>> = Mutex::new(HashMap::new());
+	// exceptions added from private tabs
+	static ref PRIVATE_EXCEPTIONS: Mutex<Vec<CertException>> = Mutex::new(Vec::new());
+	// mesh -> CA in PEM, `None` when the agent could not provide it
+	static ref MESH_CAS: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+	// `label host fingerprint` already let through, so a certificate that still fails is not retried forever
+	static ref ALLOWED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn trust_key(mesh: &str) -> String {
+	format!("mesh-ca-trust-{}", mesh)
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+pub(crate) fn fingerprint(der: &[u8]) -> String {
+	Sha256::digest(der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+// Fingerprint of the first certificate of a PEM bundle.
+fn pem_fingerprint(pem: &str) -> Option<String> {
+	let body: String = pem
+		.lines()
+		.skip_while(|l| !l.starts_with("-----BEGIN CERTIFICATE-----"))
+		.skip(1)
+		.take_while(|l| !l.starts_with("-----END"))
+		.collect();
+	let der = base64::engine::general_purpose::STANDARD.decode(body.trim()).ok()?;
+	Some(fingerprint(&der))
+}
+
+fn load_exceptions(app: &AppHandle) -> Vec<CertException> {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(EXCEPTIONS_KEY))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default()
+}
+
+fn save_exceptions(app: &AppHandle, exceptions: &[CertException]) -> Result<(), String> {
+	let store = store::open_store(app)?;
+	store.set(EXCEPTIONS_KEY, serde_json::to_value(exceptions).map_err(|e| e.to_string())?);
+	store::notify_store_change(app, EXCEPTIONS_KEY, "set");
+	Ok(())
+}
+
+// Exceptions added in private tabs only apply to private tabs.
+fn has_exception(app: &AppHandle, label: &str, host: &str, fingerprint: &str) -> bool {
+	let matches = |e: &CertException| e.host == host && e.fingerprint == fingerprint;
+	(tabs::is_private(label) && PRIVATE_EXCEPTIONS.lock().unwrap().iter().any(matches))
+		|| load_exceptions(app).iter().any(matches)
+}
+
+fn trusts_mesh_ca(app: &AppHandle, mesh: &str) -> bool {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(trust_key(mesh)))
+		.and_then(|value| value.as_bool())
+		.unwrap_or(false)
+}
+
+async fn fetch_mesh_ca(app: &AppHandle, mesh: &str) -> Result<String, String> {
+	let pem = agent::get(app, &["api", "meshes", mesh, "ca"])
+		.await?
+		.text()
+		.await
+		.map_err(|e| e.to_string())?;
+	if pem_fingerprint(&pem).is_none() {
+		return Err(format!("The agent returned no certificate for the CA of {}", mesh));
+	}
+	MESH_CAS.lock().unwrap().insert(mesh.to_string(), Some(pem.clone()));
+	Ok(pem)
+}
+
+// Mesh whose CA may vouch for the certificate a tab got: the tab's mesh, when the
+// request went through its proxy and the user trusts that mesh's CA.
+fn trusted_mesh(app: &AppHandle, label: &str) -> Option<String> {
+	let tab = tabs::find_tab(label)?;
+	if tab.mesh.is_empty() || tab.proxy.is_empty() || !trusts_mesh_ca(app, &tab.mesh) {
+		return None;
+	}
+	Some(tab.mesh)
+}
+
+enum Verdict {
+	Allow,
+	// CA of the mesh still to be fetched, the load is retried once it is
+	FetchCa(String),
+	Interstitial(String),
+}
+
+// What to do about a certificate that failed to verify. `ca_accepts` checks it
+// against a CA in PEM.
+fn judge(
+	app: &AppHandle,
+	label: &str,
+	url: &Url,
+	fingerprint: &str,
+	problems: &[&str],
+	ca_accepts: impl Fn(&str) -> bool,
+) -> Verdict {
+	let host = url.host_str().unwrap_or_default().to_string();
+	let seen = format!("{} {} {}", label, host, fingerprint);
+	let retried = ALLOWED.lock().unwrap().contains(&seen);
+	let mesh = trusted_mesh(app, label);
+	let mut note = None;
+	if !retried {
+		if has_exception(app, label, &host, fingerprint) {
+			ALLOWED.lock().unwrap().insert(seen);
+			return Verdict::Allow;
+		}
+		if let Some(mesh) = &mesh {
+			match MESH_CAS.lock().unwrap().get(mesh) {
+				None => return Verdict::FetchCa(mesh.clone()),
+				Some(Some(ca)) if ca_accepts(ca) => {
+					ALLOWED.lock().unwrap().insert(seen);
+					return Verdict::Allow;
+				}
+				Some(Some(_)) => note = Some(format!("The certificate is not issued by the CA of mesh {}.", mesh)),
+				Some(None) => note = Some(format!("The CA of mesh {} could not be fetched from the agent.", mesh)),
+			}
+		}
+	}
+	let problem = CertProblem {
+		label: label.to_string(),
+		url: url.to_string(),
+		host,
+		fingerprint: fingerprint.to_string(),
+	};
+	let token: String = rand::random::<[u8; 16]>().iter().map(|b| format!("{:02x}", b)).collect();
+	let page = interstitial(&token, &problem, problems, note.as_deref());
+	// one interstitial per webview at a time
+	let mut pending = PROBLEMS.lock().unwrap();
+	pending.retain(|_, p| p.label != label);
+	pending.insert(token, problem);
+	Verdict::Interstitial(page)
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn interstitial(token: &str, problem: &CertProblem, problems: &[&str], note: Option<&str>) -> String {
+	let items: String = problems.iter().map(|p| format!("<li>{}</li>", escape_html(p))).collect();
+	let note = note.map(|n| format!("<p>{}</p>", escape_html(n))).unwrap_or_default();
+	format!(
+		r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Certificate not trusted</title>
+<style>
+	body {{ font-family: "Helvetica Neue", Arial, "Noto Sans", sans-serif; margin: 0; padding: 80px 40px; color: #333; }}
+	h1 {{ font-size: 20px; margin: 0 0 12px; }}
+	p, li {{ font-size: 14px; line-height: 20px; margin: 0 0 8px; }}
+	code {{ background: #f2f2f2; padding: 2px 6px; border-radius: 4px; word-break: break-all; }}
+	.actions {{ margin-top: 24px; }}
+	.actions a {{ display: inline-block; padding: 6px 14px; margin-right: 8px; border-radius: 6px; text-decoration: none; font-size: 14px; }}
+	.back {{ background: #9a61ff; color: #fff; }}
+	.trust {{ color: #c0392b; border: 1px solid #c0392b; }}
+	@media (prefers-color-scheme: dark) {{ body {{ background: #18181a; color: #eee; }} code {{ background: #2b2b29; }} }}
+</style></head><body>
+<h1>The certificate of {host} is not trusted</h1>
+<ul>{items}</ul>
+{note}
+<p>SHA-256 fingerprint</p>
+<p><code>{fingerprint}</code></p>
+<p>Compare it with the one of the service before trusting it.</p>
+<div class="actions"><a class="back" href="{scheme}://back/{token}">Go back</a><a class="trust" href="{scheme}://trust/{token}">Trust this certificate for {host}</a></div>
+</body></html>"#,
+		host = escape_html(&problem.host),
+		items = items,
+		note = note,
+		fingerprint = escape_html(&problem.fingerprint),
+		scheme = ACTION_SCHEME,
+		token = token,
+	)
+}
+
+pub(crate) fn is_action_url(url: &Url) -> bool {
+	url.scheme() == ACTION_SCHEME
+}
+
+// Called for the other navigations of `label`: once it leaves the interstitial its
+// buttons are void. Loading the failing URL again keeps them, the interstitial is
+// shown under that URL.
+pub(crate) fn left_page(label: &str, url: &Url) {
+	PROBLEMS.lock().unwrap().retain(|_, p| p.label != label || p.url == url.as_str());
+}
+
+// Handles a button of the interstitial shown in `label`.
+pub(crate) fn handle_action(app: &AppHandle, label: &str, url: &Url) {
+	let token = url.path().trim_start_matches('/');
+	let problem = {
+		let mut problems = PROBLEMS.lock().unwrap();
+		match problems.get(token) {
+			Some(problem) if problem.label == label => problems.remove(token),
+			_ => None,
+		}
+	};
+	let (Some(problem), Some(webview)) = (problem, app.get_webview(label)) else {
+		return;
+	};
+	let result = match url.host_str() {
+		Some("trust") => {
+			let exception = CertException {
+				host: problem.host.clone(),
+				fingerprint: problem.fingerprint.clone(),
+				added: now(),
+			};
+			if tabs::is_private(label) {
+				PRIVATE_EXCEPTIONS.lock().unwrap().push(exception);
+			} else {
+				let mut exceptions = load_exceptions(app);
+				exceptions.push(exception);
+				if let Err(e) = save_exceptions(app, &exceptions) {
+					warn!("failed to save the certificate exception for {}: {}", problem.host, e);
+				}
+			}
+			info!("certificate {} trusted for {}", problem.fingerprint, tabs::loggable(label, &problem.host));
+			// the load fails again and is let through by the exception
+			ALLOWED.lock().unwrap().remove(&format!("{} {} {}", label, problem.host, problem.fingerprint));
+			navigation::approve(label, &problem.url);
+			Url::parse(&problem.url).map_err(|e| e.to_string()).and_then(|u| webview.navigate(u).map_err(|e| e.to_string()))
+		}
+		_ => webview.eval("history.back()").map_err(|e| e.to_string()),
+	};
+	if let Err(e) = result {
+		warn!("certificate interstitial action failed in {}: {}", label, e);
+	}
+}
+
+// Called for every browser webview once it is created.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch(app: &AppHandle, webview: &Webview) {
+	use webkit2gtk::gio;
+	use webkit2gtk::gio::prelude::TlsCertificateExt;
+	use webkit2gtk::{WebContextExt, WebViewExt};
+
+	let app = app.clone();
+	let label = webview.label().to_string();
+	let result = webview.with_webview(move |platform| {
+		platform.inner().connect_load_failed_with_tls_errors(move |view, failing_uri, certificate, errors| {
+			let Ok(url) = Url::parse(failing_uri) else {
+				return false;
+			};
+			let Some(der) = certificate.certificate() else {
+				return false;
+			};
+			let host = url.host_str().unwrap_or_default().trim_matches(|c| c == '[' || c == ']').to_string();
+			let problems: Vec<&str> = [
+				(gio::TlsCertificateFlags::UNKNOWN_CA, "It is not issued by a trusted certificate authority."),
+				(gio::TlsCertificateFlags::BAD_IDENTITY, "It is not valid for this host name."),
+				(gio::TlsCertificateFlags::NOT_ACTIVATED, "It is not valid yet."),
+				(gio::TlsCertificateFlags::EXPIRED, "It has expired."),
+				(gio::TlsCertificateFlags::REVOKED, "It has been revoked."),
+				(gio::TlsCertificateFlags::INSECURE, "It uses an insecure algorithm."),
+				(gio::TlsCertificateFlags::GENERIC_ERROR, "It could not be verified."),
+			]
+			.into_iter()
+			.filter(|(flag, _)| errors.contains(*flag))
+			.map(|(_, text)| text)
+			.collect();
+			let ca_accepts = |pem: &str| {
+				let Ok(ca) = gio::TlsCertificate::from_pem(pem) else {
+					return false;
+				};
+				let identity = gio::NetworkAddress::new(&host, url.port_or_known_default().unwrap_or(443));
+				certificate.verify(Some(&identity), Some(&ca)).is_empty()
+			};
+			match judge(&app, &label, &url, &fingerprint(&der), &problems, ca_accepts) {
+				Verdict::Allow => {
+					if let Some(context) = view.context() {
+						context.allow_tls_certificate_for_host(certificate, &host);
+					}
+					navigation::approve(&label, failing_uri);
+					view.load_uri(failing_uri);
+				}
+				Verdict::FetchCa(mesh) => {
+					let app = app.clone();
+					let label = label.clone();
+					tauri::async_runtime::spawn(async move {
+						if let Err(e) = fetch_mesh_ca(&app, &mesh).await {
+							warn!("failed to fetch the CA of {}: {}", mesh, e);
+							MESH_CAS.lock().unwrap().insert(mesh, None);
+						}
+						if let Some(webview) = app.get_webview(&label) {
+							navigation::approve(&label, url.as_str());
+							if let Err(e) = webview.navigate(url) {
+								warn!("failed to reload {}: {}", label, e);
+							}
+						}
+					});
+				}
+				Verdict::Interstitial(page) => {
+					navigation::approve(&label, failing_uri);
+					view.load_alternate_html(&page, failing_uri, None);
+				}
+			}
+			true
+		});
+	});
+	if let Err(e) = result {
+		warn!("failed to watch the certificates of {}: {}", webview.label(), e);
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn watch(_app: &AppHandle, webview: &Webview) {
+	info!("certificate exceptions are not supported on this platform, {} keeps the default handling", webview.label());
+}
+
+#[command]
+pub async fn list_cert_exceptions(
+	app: AppHandle,
+) -> Result<Vec<CertException>, String> {
+	Ok(load_exceptions(&app))
+}
+
+// Removes the exceptions of `host`, or only the one for `fingerprint`. WebKit offers no
+// way to withdraw a certificate once allowed for a host, so webviews that already
+// accepted it keep doing so until the app restarts; new webviews ask again.
+#[command]
+pub async fn remove_cert_exception(
+	app: AppHandle,
+	host: String,
+	fingerprint: Option<String>,
+) -> Result<(), String> {
+	let keep = |e: &CertException| e.host != host || fingerprint.as_ref().is_some_and(|f| *f != e.fingerprint);
+	PRIVATE_EXCEPTIONS.lock().unwrap().retain(keep);
+	let mut exceptions = load_exceptions(&app);
+	exceptions.retain(keep);
+	ALLOWED.lock().unwrap().retain(|seen| seen.split(' ').nth(1) != Some(host.as_str()));
+	save_exceptions(&app, &exceptions)
+}
+
+#[command]
+pub async fn get_mesh_ca_trust(
+	app: AppHandle,
+	mesh: String,
+) -> Result<bool, String> {
+	Ok(trusts_mesh_ca(&app, &mesh))
+}
+
+// Trusting the CA fetches it right away, so an agent that cannot provide it is reported.
+#[command]
+pub async fn set_mesh_ca_trust(
+	app: AppHandle,
+	mesh: String,
+	trust: bool,
+) -> Result<(), String> {
+	MESH_CAS.lock().unwrap().remove(&mesh);
+	if trust {
+		fetch_mesh_ca(&app, &mesh).await?;
+	}
+	let key = trust_key(&mesh);
+	let store = store::open_store(&app)?;
+	store.set(&key, trust);
+	store::notify_store_change(&app, &key, "set");
+	Ok(())
+}
+
+// The CA of `mesh` as served by the agent, to show its fingerprint before trusting it.
+#[command]
+pub async fn get_mesh_ca(
+	app: AppHandle,
+	mesh: String,
+) -> Result<MeshCa, String> {
+	let pem = fetch_mesh_ca(&app, &mesh).await?;
+	let fingerprint = pem_fingerprint(&pem).unwrap_or_default();
+	Ok(MeshCa { pem, fingerprint })
+}
@@ -0,0 +1,444 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::webview::DownloadEvent;
+use tauri::{Emitter, EventTarget, Webview};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri_plugin_dialog::DialogExt;
+use url::Url;
+use log::warn;
+
+use crate::scope;
+use crate::store;
+use crate::tabs;
+
+// Downloads started in browser webviews. The webview engines only report when a
+// download is requested and when it ends, so progress is the size of the file on
+// disk, polled while it is running and kept in memory. Starts and ends are persisted
+// in the `downloads` store list; every change is emitted as `download-progress` for
+// the toolbar. Downloads of private tabs are only kept in memory until the tab closes.
+pub const DOWNLOAD_EVENT: &str = "download-progress";
+const DOWNLOADS_KEY: &str = "downloads";
+const DOWNLOAD_SETTINGS_KEY: &str = "download-settings";
+const MAX_DOWNLOADS: usize = 200;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// progress polling gives up on a download whose file has not grown for this long
+const STALL_TIMEOUT: Duration = Duration::from_secs(600);
+// temporary names engines write to before moving the file into place
+const PARTIAL_SUFFIXES: &[&str] = &[".crdownload", ".download", ".part"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadState {
+	Downloading,
+	Completed,
+	Failed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Download {
+	pub id: String,
+	pub url: String,
+	pub path: String,
+	// webview the download started in, used to retry with the same proxy and cookies
+	pub webview: String,
+	pub state: DownloadState,
+	pub received: u64,
+	pub started_at: u64,
+	pub finished_at: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSettings {
+	// defaults to the system download directory
+	#[serde(default)]
+	pub directory: Option<String>,
+	// ask for a save location on every download
+	#[serde(default)]
+	pub ask: bool,
+}
+
+lazy_static! {
+	// id -> downloads in progress, with their latest progress
+	static ref RUNNING: Mutex<HashMap<String, Download>> = Mutex::new(HashMap::new());
+	// url -> destination chosen in a save dialog or kept for a retry
+	static ref DESTINATIONS: Mutex<HashMap<String, PathBuf>> = Mutex::new(HashMap::new());
+	// serialises read-modify-write of the persisted list between the hook and pollers
+	static ref LIST: Mutex<()> = Mutex::new(());
+	// id -> finished downloads of private tabs
+	static ref PRIVATE: Mutex<HashMap<String, Download>> = Mutex::new(HashMap::new());
+}
+static NEXT_DOWNLOAD: AtomicU64 = AtomicU64::new(1);
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn load_settings(app: &AppHandle) -> DownloadSettings {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(DOWNLOAD_SETTINGS_KEY))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default()
+}
+
+fn download_dir(app: &AppHandle) -> Result<PathBuf, String> {
+	match load_settings(app).directory.filter(|d| !d.is_empty()) {
+		Some(dir) => Ok(PathBuf::from(dir)),
+		None => app.path().download_dir().map_err(|e| e.to_string()),
+	}
+}
+
+fn file_name(url: &Url, suggested: &Path) -> String {
+	suggested
+		.file_name()
+		.map(|n| n.to_string_lossy().to_string())
+		.or_else(|| url.path_segments().and_then(|mut s| s.next_back()).map(|s| s.to_string()))
+		.filter(|n| !n.is_empty())
+		.unwrap_or_else(|| "download".to_string())
+}
+
+// `name`, or `name (1)`, `name (2)`... so existing files are never overwritten.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+	let candidate = dir.join(name);
+	if !candidate.exists() {
+		return candidate;
+	}
+	let path = Path::new(name);
+	let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+	let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+	(1..)
+		.map(|i| dir.join(format!("{} ({}){}", stem, i, ext)))
+		.find(|p| !p.exists())
+		.unwrap()
+}
+
+fn load_downloads(app: &AppHandle) -> Vec<Download> {
+	match store::open_store(app) {
+		Ok(store) => store::read_list(&store, DOWNLOADS_KEY)
+			.into_iter()
+			.filter_map(|v| serde_json::from_value(v).ok())
+			.collect(),
+		Err(_) => vec![],
+	}
+}
+
+fn update_downloads(app: &AppHandle, f: impl FnOnce(&mut Vec<Download>)) {
+	let _guard = LIST.lock().unwrap();
+	let store = match store::open_store(app) {
+		Ok(store) => store,
+		Err(e) => {
+			warn!("failed to persist downloads: {}", e);
+			return;
+		}
+	};
+	let mut downloads = load_downloads(app);
+	f(&mut downloads);
+	if downloads.len() > MAX_DOWNLOADS {
+		let excess = downloads.len() - MAX_DOWNLOADS;
+		downloads.drain(..excess);
+	}
+	let value = serde_json::to_value(&downloads).unwrap_or(JsonValue::Array(vec![]));
+	store.set(DOWNLOADS_KEY, value);
+	store::notify_store_change(app, DOWNLOADS_KEY, "set");
+}
+
+// Only the main window and the toolbar of the page the download came from hear of it,
+// private downloads only the latter.
+fn emit_progress(app: &AppHandle, download: &Download) {
+	let mut targets = vec![EventTarget::webview(&download.webview)];
+	if !tabs::is_private(&download.webview) {
+		targets.push(EventTarget::labeled(scope::MAIN_WINDOW));
+	}
+	for target in targets {
+		if let Err(e) = app.emit_to(target, DOWNLOAD_EVENT, download) {
+			warn!("failed to emit {}: {}", DOWNLOAD_EVENT, e);
+		}
+	}
+}
+
+// Inserts or replaces `download` in the persisted list and tells the toolbar.
+fn publish(app: &AppHandle, download: &Download) {
+	if tabs::is_private(&download.webview) {
+		if download.state != DownloadState::Downloading {
+			PRIVATE.lock().unwrap().insert(download.id.clone(), download.clone());
+		}
+		emit_progress(app, download);
+		return;
+	}
+	update_downloads(app, |downloads| match downloads.iter_mut().find(|d| d.id == download.id) {
+		Some(existing) => *existing = download.clone(),
+		None => downloads.push(download.clone()),
+	});
+	emit_progress(app, download);
+}
+
+// The persisted entry, with the progress of a running download.
+fn find_download(app: &AppHandle, id: &str) -> Option<Download> {
+	if let Some(download) = RUNNING.lock().unwrap().get(id).or(PRIVATE.lock().unwrap().get(id)) {
+		return Some(download.clone());
+	}
+	load_downloads(app).into_iter().find(|d| d.id == id)
+}
+
+// Drops the private downloads of webview `label` once it is closed.
+pub(crate) fn forget(label: &str) {
+	PRIVATE.lock().unwrap().retain(|_, d| d.webview != label);
+}
+
+// Downloads still marked running were cut short when the app last quit.
+pub fn init(app: &AppHandle) {
+	if !load_downloads(app).iter().any(|d| d.state == DownloadState::Downloading) {
+		return;
+	}
+	update_downloads(app, |downloads| {
+		for download in downloads.iter_mut().filter(|d| d.state == DownloadState::Downloading) {
+			download.state = DownloadState::Failed;
+			download.finished_at.get_or_insert_with(now);
+		}
+	});
+}
+
+fn on_disk_size(path: &Path) -> u64 {
+	let partials = PARTIAL_SUFFIXES
+		.iter()
+		.map(|suffix| PathBuf::from(format!("{}{}", path.display(), suffix)));
+	std::iter::once(path.to_path_buf())
+		.chain(partials)
+		.filter_map(|p| fs::metadata(p).ok())
+		.map(|m| m.len())
+		.max()
+		.unwrap_or(0)
+}
+
+// Polls the size of the file being written until the download leaves RUNNING, its
+// webview is gone or the file stops growing for STALL_TIMEOUT. `Finished` still
+// publishes the outcome whenever it comes.
+fn watch_progress(app: AppHandle, id: String) {
+	thread::spawn(move || {
+		let mut last = 0;
+		let mut grown = Instant::now();
+		loop {
+			thread::sleep(POLL_INTERVAL);
+			let Some((path, webview)) = RUNNING.lock().unwrap().get(&id).map(|d| (d.path.clone(), d.webview.clone())) else {
+				return;
+			};
+			if app.get_webview(&webview).is_none() {
+				return;
+			}
+			let received = on_disk_size(Path::new(&path));
+			if received == last {
+				if grown.elapsed() >= STALL_TIMEOUT {
+					warn!("download {} stalled, no longer polling its progress", id);
+					return;
+				}
+				continue;
+			}
+			last = received;
+			grown = Instant::now();
+			let download = RUNNING.lock().unwrap().get_mut(&id).map(|download| {
+				download.received = received;
+				download.clone()
+			});
+			if let Some(download) = download {
+				emit_progress(&app, &download);
+			}
+		}
+	});
+}
+
+// Starts the download again from the webview it came from, so it goes through the
+// same proxy and browsing profile. The anchor is never attached to the document and
+// therefore escapes the toolbar's link interception.
+fn trigger_download(webview: &Webview, url: &str) -> Result<(), String> {
+	let script = format!(
+		"(() => {{ const a = document.createElement('a'); a.href = {}; a.download = ''; a.click(); }})()",
+		serde_json::to_string(url).map_err(|e| e.to_string())?
+	);
+	webview.eval(&script).map_err(|e| e.to_string())
+}
+
+fn ask_destination(webview: Webview, url: Url, suggested: PathBuf) {
+	let app = webview.app_handle().clone();
+	let mut dialog = app.dialog().file().set_file_name(file_name(&url, &suggested));
+	if let Ok(dir) = download_dir(&app) {
+		dialog = dialog.set_directory(dir);
+	}
+	dialog.save_file(move |chosen| {
+		let Some(path) = chosen.and_then(|p| p.into_path().ok()) else {
+			return;
+		};
+		DESTINATIONS.lock().unwrap().insert(url.to_string(), path);
+		if let Err(e) = trigger_download(&webview, url.as_str()) {
+			warn!("failed to start download of {}: {}", tabs::loggable(webview.label(), url.as_str()), e);
+		}
+	});
+}
+
+// `on_download` hook of browser webviews.
+pub(crate) fn handle_download(webview: Webview, event: DownloadEvent<'_>) -> bool {
+	let app = webview.app_handle().clone();
+	match event {
+		DownloadEvent::Requested { url, destination } => {
+			let chosen = DESTINATIONS.lock().unwrap().remove(url.as_str());
+			let path = match chosen {
+				Some(path) => path,
+				None if load_settings(&app).ask => {
+					// the dialog must not block the webview; the download restarts once answered
+					ask_destination(webview, url, destination.clone());
+					return false;
+				}
+				None => match download_dir(&app) {
+					Ok(dir) => unique_path(&dir, &file_name(&url, destination)),
+					Err(e) => {
+						warn!("no download directory, keeping the webview default: {}", e);
+						destination.clone()
+					}
+				},
+			};
+			*destination = path.clone();
+			let download = Download {
+				id: format!("{}-{}", now(), NEXT_DOWNLOAD.fetch_add(1, Ordering::Relaxed)),
+				url: url.to_string(),
+				path: path.to_string_lossy().to_string(),
+				webview: webview.label().to_string(),
+				state: DownloadState::Downloading,
+				received: 0,
+				started_at: now(),
+				finished_at: None,
+			};
+			RUNNING.lock().unwrap().insert(download.id.clone(), download.clone());
+			publish(&app, &download);
+			watch_progress(app, download.id);
+			true
+		}
+		DownloadEvent::Finished { url, path, success } => {
+			// macOS never reports the final path
+			let path = path.filter(|p| !p.as_os_str().is_empty()).map(|p| p.to_string_lossy().to_string());
+			let finished = {
+				let mut running = RUNNING.lock().unwrap();
+				// several downloads of one URL: the one written to `path`, else the oldest
+				let id = running
+					.values()
+					.filter(|d| d.url == url.as_str())
+					.min_by_key(|d| (path.as_ref() != Some(&d.path), d.started_at))
+					.map(|d| d.id.clone());
+				id.and_then(|id| running.remove(&id))
+			};
+			if let Some(mut download) = finished {
+				if let Some(path) = path {
+					download.path = path;
+				}
+				download.state = if success { DownloadState::Completed } else { DownloadState::Failed };
+				download.received = on_disk_size(Path::new(&download.path));
+				download.finished_at = Some(now());
+				publish(&app, &download);
+			}
+			true
+		}
+		_ => true,
+	}
+}
+
+#[command]
+pub async fn list_downloads(app: AppHandle) -> Result<Vec<Download>, String> {
+	let running: HashMap<String, Download> = RUNNING
+		.lock()
+		.unwrap()
+		.iter()
+		.filter(|(_, d)| !tabs::is_private(&d.webview))
+		.map(|(id, d)| (id.clone(), d.clone()))
+		.collect();
+	let mut downloads: Vec<Download> = load_downloads(&app)
+		.into_iter()
+		.map(|d| running.get(&d.id).cloned().unwrap_or(d))
+		.collect();
+	downloads.reverse();
+	Ok(downloads)
+}
+
+#[command]
+pub async fn retry_download(
+	app: AppHandle,
+	id: String,
+) -> Result<(), String> {
+	let download = find_download(&app, &id).ok_or_else(|| format!("No download {}", id))?;
+	if download.state == DownloadState::Downloading {
+		return Err(format!("Download {} is still running", id));
+	}
+	let webview = app
+		.get_webview(&download.webview)
+		.ok_or_else(|| format!("The page that started {} is closed", download.url))?;
+	let path = PathBuf::from(&download.path);
+	let destination = match (download.state, path.parent()) {
+		// a completed file is kept, the new copy goes next to it
+		(DownloadState::Completed, Some(dir)) => {
+			let url = Url::parse(&download.url).map_err(|e| e.to_string())?;
+			unique_path(dir, &file_name(&url, &path))
+		}
+		// the partial file of a failed attempt is replaced, along with its entry
+		_ => {
+			let _ = fs::remove_file(&path);
+			PRIVATE.lock().unwrap().remove(&id);
+			update_downloads(&app, |downloads| downloads.retain(|d| d.id != id));
+			path
+		}
+	};
+	DESTINATIONS.lock().unwrap().insert(download.url.clone(), destination);
+	trigger_download(&webview, &download.url)
+}
+
+#[command]
+pub async fn open_download_folder(
+	app: AppHandle,
+	webview: Webview,
+	id: String,
+) -> Result<(), String> {
+	let download = find_download(&app, &id)
+		// a page may only open the folder of its own downloads
+		.filter(|d| !scope::is_browser_webview(webview.label()) || d.webview == webview.label())
+		.ok_or_else(|| format!("No download {}", id))?;
+	let folder = Path::new(&download.path)
+		.parent()
+		.ok_or_else(|| format!("{} has no folder", download.path))?
+		.to_string_lossy()
+		.to_string();
+	#[allow(deprecated)]
+	tauri_plugin_shell::ShellExt::shell(&app).open(folder, None).map_err(|e| e.to_string())
+}
+
+// Forgets finished downloads; files on disk are kept.
+#[command]
+pub async fn clear_downloads(app: AppHandle) -> Result<(), String> {
+	update_downloads(&app, |downloads| downloads.retain(|d| d.state == DownloadState::Downloading));
+	Ok(())
+}
+
+#[command]
+pub async fn get_download_settings(app: AppHandle) -> Result<DownloadSettings, String> {
+	Ok(load_settings(&app))
+}
+
+#[command]
+pub async fn set_download_settings(
+	app: AppHandle,
+	settings: DownloadSettings,
+) -> Result<(), String> {
+	if let Some(dir) = settings.directory.as_deref().filter(|d| !d.is_empty() && !Path::new(d).is_dir()) {
+		return Err(format!("{} is not a directory", dir));
+	}
+	let store = store::open_store(&app)?;
+	store.set(DOWNLOAD_SETTINGS_KEY, serde_json::to_value(&settings).map_err(|e| e.to_string())?);
+	store::notify_store_change(&app, DOWNLOAD_SETTINGS_KEY, "set");
+	Ok(())
+}
@@ -0,0 +1,268 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::{LogicalPosition, LogicalSize, Window, WindowEvent};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use log::warn;
+
+use crate::proxy::{ProxyScheme, ProxySpec};
+
+// Raw wry webviews embedded in an app window by `create_wry_webview`, keyed by
+// label. wry webviews must stay on the main thread, so the registry lives there and
+// every command hops over to it. When the parent window is resized, bounds scale
+// with it, the way `auto_resize` does for tauri webviews.
+const DEFAULT_BOUNDS: Bounds = Bounds { x: 100., y: 100., width: 960., height: 800. };
+
+// Logical pixels, relative to the parent window.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Bounds {
+	pub x: f64,
+	pub y: f64,
+	pub width: f64,
+	pub height: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EmbeddedInfo {
+	pub label: String,
+	pub window: String,
+	pub url: String,
+	pub bounds: Bounds,
+	pub visible: bool,
+}
+
+struct Embedded {
+	webview: wry::WebView,
+	window: String,
+	bounds: Bounds,
+	// inner size of the parent when `bounds` were set, in logical pixels
+	parent: (f64, f64),
+	visible: bool,
+}
+
+thread_local! {
+	static EMBEDDED: RefCell<HashMap<String, Embedded>> = RefCell::new(HashMap::new());
+}
+
+lazy_static! {
+	// parent windows whose events are followed
+	static ref WATCHED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn to_rect(bounds: Bounds) -> wry::Rect {
+	wry::Rect {
+		position: LogicalPosition::new(bounds.x, bounds.y).into(),
+		size: LogicalSize::new(bounds.width, bounds.height).into(),
+	}
+}
+
+fn check_bounds(bounds: &Bounds) -> Result<(), String> {
+	if bounds.width <= 0. || bounds.height <= 0. {
+		return Err("Width and height must be positive".to_string());
+	}
+	Ok(())
+}
+
+fn logical_size(window: &Window) -> Option<(f64, f64)> {
+	let scale = window.scale_factor().ok()?;
+	let size = window.inner_size().ok()?.to_logical::<f64>(scale);
+	Some((size.width, size.height))
+}
+
+// Runs `f` on the main thread with the registry and waits for its result.
+async fn on_main<T, F>(app: &AppHandle, f: F) -> Result<T, String>
+where
+	T: Send + 'static,
+	F: FnOnce(&AppHandle, &mut HashMap<String, Embedded>) -> Result<T, String> + Send + 'static,
+{
+	let (done, result) = mpsc::channel();
+	let handle = app.clone();
+	app.run_on_main_thread(move || {
+		let _ = done.send(EMBEDDED.with(|embedded| f(&handle, &mut embedded.borrow_mut())));
+	})
+	.map_err(|e| e.to_string())?;
+	tauri::async_runtime::spawn_blocking(move || result.recv())
+		.await
+		.map_err(|e| e.to_string())?
+		.map_err(|e| e.to_string())?
+}
+
+fn with_webview<T, F>(embedded: &mut HashMap<String, Embedded>, label: &str, f: F) -> Result<T, String>
+where
+	F: FnOnce(&mut Embedded) -> wry::Result<T>,
+{
+	let entry = embedded.get_mut(label).ok_or_else(|| format!("No embedded webview {}", label))?;
+	f(entry).map_err(|e| e.to_string())
+}
+
+// Follows resizes of the parent, drops its webviews when it is destroyed. Window
+// events are delivered on the main thread.
+fn watch_parent(window: &Window) {
+	if !WATCHED.lock().unwrap().insert(window.label().to_string()) {
+		return;
+	}
+	let parent = window.clone();
+	window.on_window_event(move |event| match event {
+		WindowEvent::Resized(_) => {
+			let Some(size) = logical_size(&parent) else {
+				return;
+			};
+			EMBEDDED.with(|embedded| {
+				for (label, entry) in embedded.borrow_mut().iter_mut().filter(|(_, e)| e.window == parent.label()) {
+					let (sx, sy) = (size.0 / entry.parent.0.max(1.), size.1 / entry.parent.1.max(1.));
+					let bounds = Bounds {
+						x: entry.bounds.x * sx,
+						y: entry.bounds.y * sy,
+						width: entry.bounds.width * sx,
+						height: entry.bounds.height * sy,
+					};
+					if let Err(e) = entry.webview.set_bounds(to_rect(bounds)) {
+						warn!("failed to resize {}: {}", label, e);
+					}
+				}
+			});
+		}
+		WindowEvent::Destroyed => {
+			WATCHED.lock().unwrap().remove(parent.label());
+			EMBEDDED.with(|embedded| embedded.borrow_mut().retain(|_, e| e.window != parent.label()));
+		}
+		_ => {}
+	});
+}
+
+// Embeds a webview browsing `curl` through a SOCKS5 proxy in window `window_label`;
+// an existing webview with that label loads `curl` instead.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_wry_webview(
+	app: AppHandle,
+	label: String,
+	window_label: String,
+	proxy_host: String,
+	proxy_port: String,
+	curl: String,
+	incognito: Option<bool>,
+	bounds: Option<Bounds>,
+) -> Result<(), String> {
+	let proxy_config = ProxySpec::new(ProxyScheme::Socks5, &proxy_host, &proxy_port)
+		.map_err(|e| e.to_string())?
+		.to_wry();
+	let bounds = bounds.unwrap_or(DEFAULT_BOUNDS);
+	check_bounds(&bounds)?;
+	on_main(&app, move |app, embedded| {
+		if let Some(entry) = embedded.get(&label) {
+			return entry.webview.load_url(&curl).map_err(|e| e.to_string());
+		}
+		let window = app
+			.get_window(&window_label)
+			.ok_or_else(|| format!("No window {}", window_label))?;
+		let webview = wry::WebViewBuilder::new()
+			.with_url(&curl)
+			.with_proxy_config(proxy_config)
+			.with_incognito(incognito.unwrap_or(false))
+			.with_bounds(to_rect(bounds))
+			.build_as_child(&window)
+			.map_err(|e| e.to_string())?;
+		watch_parent(&window);
+		embedded.insert(label, Embedded {
+			webview,
+			window: window_label,
+			bounds,
+			parent: logical_size(&window).unwrap_or((bounds.width, bounds.height)),
+			visible: true,
+		});
+		Ok(())
+	})
+	.await
+}
+
+#[command]
+pub async fn list_wry_webviews(
+	app: AppHandle,
+) -> Result<Vec<EmbeddedInfo>, String> {
+	on_main(&app, |_, embedded| {
+		Ok(embedded
+			.iter()
+			.map(|(label, e)| EmbeddedInfo {
+				label: label.clone(),
+				window: e.window.clone(),
+				url: e.webview.url().unwrap_or_default(),
+				bounds: e.bounds,
+				visible: e.visible,
+			})
+			.collect())
+	})
+	.await
+}
+
+// Bounds are taken relative to the current size of the parent window.
+#[command]
+pub async fn set_wry_webview_bounds(
+	app: AppHandle,
+	label: String,
+	bounds: Bounds,
+) -> Result<(), String> {
+	check_bounds(&bounds)?;
+	on_main(&app, move |app, embedded| {
+		let parent = embedded.get(&label).and_then(|e| app.get_window(&e.window)).and_then(|w| logical_size(&w));
+		with_webview(embedded, &label, |entry| {
+			entry.bounds = bounds;
+			if let Some(parent) = parent {
+				entry.parent = parent;
+			}
+			entry.webview.set_bounds(to_rect(entry.bounds))
+		})
+	})
+	.await
+}
+
+#[command]
+pub async fn set_wry_webview_visible(
+	app: AppHandle,
+	label: String,
+	visible: bool,
+) -> Result<(), String> {
+	on_main(&app, move |_, embedded| {
+		with_webview(embedded, &label, |entry| {
+			entry.visible = visible;
+			entry.webview.set_visible(visible)
+		})
+	})
+	.await
+}
+
+#[command]
+pub async fn focus_wry_webview(
+	app: AppHandle,
+	label: String,
+) -> Result<(), String> {
+	on_main(&app, move |_, embedded| with_webview(embedded, &label, |entry| entry.webview.focus())).await
+}
+
+#[command]
+pub async fn reload_wry_webview(
+	app: AppHandle,
+	label: String,
+) -> Result<(), String> {
+	on_main(&app, move |_, embedded| with_webview(embedded, &label, |entry| entry.webview.reload())).await
+}
+
+// Dropping a wry webview destroys it.
+#[command]
+pub async fn destroy_wry_webview(
+	app: AppHandle,
+	label: String,
+) -> Result<(), String> {
+	on_main(&app, move |_, embedded| {
+		embedded
+			.remove(&label)
+			.map(|_| ())
+			.ok_or_else(|| format!("No embedded webview {}", label))
+	})
+	.await
+}
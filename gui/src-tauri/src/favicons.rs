@@ -0,0 +1,334 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::UriSchemeContext;
+use tauri_plugin_http::reqwest;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+use log::{info, warn};
+
+use crate::bookmarks;
+use crate::proxy::{parse_proxy, ProxyScheme};
+
+// Bookmark icons are fetched once, through the proxy of the page they were
+// bookmarked from, and kept under `<AppCache>/favicons`. Pages and the main window
+// load them as `ztm-icon://localhost/<key>`, which keeps working while the mesh is
+// offline. `index.json` records where each icon came from and when it was last
+// served; icons no bookmark points to are evicted by age and total size.
+pub const ICON_SCHEME: &str = "ztm-icon";
+const CACHE_DIR: &str = "favicons";
+const INDEX_FILE: &str = "index.json";
+const MAX_ICON_BYTES: usize = 512 * 1024;
+const MAX_CACHE_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_AGE: Duration = Duration::from_secs(90 * 24 * 3600);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+// `accessed_at` is written back to `index.json` at most this often per icon
+const ACCESS_PRECISION: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IconEntry {
+	pub source: String,
+	pub content_type: String,
+	pub size: u64,
+	pub fetched_at: u64,
+	pub accessed_at: u64,
+}
+
+lazy_static! {
+	// key -> entry, loaded from `index.json` on first use
+	static ref INDEX: Mutex<Option<HashMap<String, IconEntry>>> = Mutex::new(None);
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+	Ok(app.path().app_cache_dir().map_err(|e| e.to_string())?.join(CACHE_DIR))
+}
+
+fn icon_key(source: &str) -> String {
+	Sha256::digest(source.as_bytes())[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Windows and Android webviews only reach custom schemes through `http://<scheme>.localhost`.
+pub fn icon_url(key: &str) -> String {
+	if cfg!(any(windows, target_os = "android")) {
+		format!("http://{}.localhost/{}", ICON_SCHEME, key)
+	} else {
+		format!("{}://localhost/{}", ICON_SCHEME, key)
+	}
+}
+
+fn key_of(icon: &str) -> Option<&str> {
+	icon
+		.strip_prefix(&format!("{}://localhost/", ICON_SCHEME))
+		.or_else(|| icon.strip_prefix(&format!("http://{}.localhost/", ICON_SCHEME)))
+}
+
+fn with_index<T>(app: &AppHandle, f: impl FnOnce(&mut HashMap<String, IconEntry>) -> T) -> T {
+	let mut index = INDEX.lock().unwrap();
+	let entries = index.get_or_insert_with(|| {
+		cache_dir(app)
+			.ok()
+			.and_then(|dir| fs::read(dir.join(INDEX_FILE)).ok())
+			.and_then(|bytes| serde_json::from_slice(&bytes).ok())
+			.unwrap_or_default()
+	});
+	f(entries)
+}
+
+fn save_index(app: &AppHandle) {
+	let result = with_index(app, |entries| -> Result<(), String> {
+		let dir = cache_dir(app)?;
+		fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+		fs::write(dir.join(INDEX_FILE), serde_json::to_vec(entries).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+	});
+	if let Err(e) = result {
+		warn!("failed to save favicon index: {}", e);
+	}
+}
+
+// Content type from the response, or from the first bytes when servers send
+// `application/octet-stream` for `/favicon.ico`.
+fn sniff_content_type(bytes: &[u8], declared: Option<&str>) -> Option<String> {
+	if let Some(declared) = declared.filter(|t| t.starts_with("image/")) {
+		return Some(declared.split(';').next().unwrap_or(declared).trim().to_string());
+	}
+	let kind = if bytes.starts_with(b"\x89PNG") {
+		"image/png"
+	} else if bytes.starts_with(&[0, 0, 1, 0]) {
+		"image/x-icon"
+	} else if bytes.starts_with(b"GIF8") {
+		"image/gif"
+	} else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+		"image/jpeg"
+	} else if bytes.len() > 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+		"image/webp"
+	} else if String::from_utf8_lossy(&bytes[..bytes.len().min(256)]).contains("<svg") {
+		"image/svg+xml"
+	} else {
+		return None;
+	};
+	Some(kind.to_string())
+}
+
+fn http_client(proxy: &str) -> Result<reqwest::Client, String> {
+	let mut builder = reqwest::Client::builder().timeout(FETCH_TIMEOUT);
+	if !proxy.is_empty() {
+		let spec = parse_proxy(proxy).map_err(|e| e.to_string())?;
+		// let the proxy resolve names, mesh hosts are unknown to the local resolver
+		let url = match spec.scheme {
+			ProxyScheme::Socks5 => format!("socks5h://{}", spec.authority()),
+			ProxyScheme::Http => spec.to_url().to_string(),
+		};
+		builder = builder.proxy(reqwest::Proxy::all(url).map_err(|e| e.to_string())?);
+	}
+	builder.build().map_err(|e| e.to_string())
+}
+
+async fn fetch_icon(client: &reqwest::Client, source: &str) -> Result<(Vec<u8>, String), String> {
+	let response = client.get(source).send().await.map_err(|e| e.to_string())?;
+	if !response.status().is_success() {
+		return Err(format!("{} answered {}", source, response.status()));
+	}
+	let declared = response
+		.headers()
+		.get(reqwest::header::CONTENT_TYPE)
+		.and_then(|v| v.to_str().ok())
+		.map(|v| v.to_string());
+	let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+	if bytes.is_empty() || bytes.len() > MAX_ICON_BYTES {
+		return Err(format!("{} is not a usable icon ({} bytes)", source, bytes.len()));
+	}
+	let content_type = sniff_content_type(&bytes, declared.as_deref())
+		.ok_or_else(|| format!("{} is not an image", source))?;
+	Ok((bytes.to_vec(), content_type))
+}
+
+// Fetches the icon of `page` (its declared `icon`, else `/favicon.ico` of its
+// origin) through `proxy` and returns the `ztm-icon://` URL it is served from.
+pub(crate) async fn cache_icon(app: &AppHandle, page: &str, icon: &str, proxy: &str) -> Result<String, String> {
+	let mut sources: Vec<String> = vec![];
+	let declared = Url::parse(icon).or_else(|_| Url::parse(page).and_then(|p| p.join(icon))).ok();
+	if let Some(url) = declared.filter(|u| u.scheme() == "http" || u.scheme() == "https") {
+		sources.push(url.to_string());
+	}
+	let fallback = Url::parse(page).and_then(|p| p.join("/favicon.ico")).ok();
+	if let Some(url) = fallback.filter(|u| !sources.contains(&u.to_string())) {
+		sources.push(url.to_string());
+	}
+	let client = http_client(proxy)?;
+	let mut last_error = format!("{} has no icon", page);
+	for source in sources {
+		let key = icon_key(&source);
+		let dir = cache_dir(app)?;
+		if dir.join(&key).exists() && with_index(app, |entries| entries.contains_key(&key)) {
+			return Ok(icon_url(&key));
+		}
+		match fetch_icon(&client, &source).await {
+			Ok((bytes, content_type)) => {
+				fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+				fs::write(dir.join(&key), &bytes).map_err(|e| e.to_string())?;
+				with_index(app, |entries| {
+					entries.insert(key.clone(), IconEntry {
+						source,
+						content_type,
+						size: bytes.len() as u64,
+						fetched_at: now(),
+						accessed_at: now(),
+					});
+				});
+				evict(app);
+				return Ok(icon_url(&key));
+			}
+			Err(e) => last_error = e,
+		}
+	}
+	Err(last_error)
+}
+
+// Caches the icon of a freshly added bookmark in the background and points the
+// bookmark at the local copy once it is there. Failures of bookmarks added from a
+// `private` tab are logged without their URLs.
+pub(crate) fn cache_bookmark_icon(app: &AppHandle, bookmark: &bookmarks::Bookmark, proxy: String, private: bool) {
+	if key_of(&bookmark.icon).is_some() {
+		return;
+	}
+	let app = app.clone();
+	let id = bookmark.id.clone();
+	let page = bookmark.url.clone();
+	let icon = bookmark.icon.clone();
+	tauri::async_runtime::spawn(async move {
+		match cache_icon(&app, &page, &icon, &proxy).await {
+			Ok(local) => {
+				if let Err(e) = bookmarks::set_icon(&app, &id, &local) {
+					warn!("failed to update icon of bookmark {}: {}", id, e);
+				}
+			}
+			Err(_) if private => warn!("favicon of bookmark {} not cached", id),
+			Err(e) => warn!("favicon of {} not cached: {}", page, e),
+		}
+	});
+}
+
+fn referenced_keys(app: &AppHandle) -> HashSet<String> {
+	bookmarks::icons(app)
+		.iter()
+		.filter_map(|icon| key_of(icon).map(|k| k.to_string()))
+		.collect()
+}
+
+// Drops icons no bookmark uses once they are older than `MAX_AGE`, then the least
+// recently served ones until the cache fits in `MAX_CACHE_BYTES`. Files missing from
+// the index are leftovers and go too.
+fn evict(app: &AppHandle) -> usize {
+	let Ok(dir) = cache_dir(app) else {
+		return 0;
+	};
+	let keep = referenced_keys(app);
+	let removed: Vec<String> = with_index(app, |entries| {
+		let mut removed = vec![];
+		let cutoff = now().saturating_sub(MAX_AGE.as_secs());
+		let mut candidates: Vec<(String, u64, u64)> = entries
+			.iter()
+			.filter(|(key, _)| !keep.contains(*key))
+			.map(|(key, e)| (key.clone(), e.accessed_at, e.size))
+			.collect();
+		candidates.sort_by_key(|(_, accessed_at, _)| *accessed_at);
+		let mut total: u64 = entries.values().map(|e| e.size).sum();
+		for (key, accessed_at, size) in candidates {
+			if accessed_at >= cutoff && total <= MAX_CACHE_BYTES {
+				continue;
+			}
+			entries.remove(&key);
+			total = total.saturating_sub(size);
+			removed.push(key);
+		}
+		if let Ok(files) = fs::read_dir(&dir) {
+			for file in files.flatten() {
+				let name = file.file_name().to_string_lossy().to_string();
+				if name != INDEX_FILE && !entries.contains_key(&name) {
+					removed.push(name);
+				}
+			}
+		}
+		removed
+	});
+	for key in &removed {
+		let _ = fs::remove_file(dir.join(key));
+	}
+	save_index(app);
+	removed.len()
+}
+
+fn respond(status: StatusCode, content_type: &str, body: Vec<u8>) -> Response<Cow<'static, [u8]>> {
+	Response::builder()
+		.status(status)
+		.header(header::CONTENT_TYPE, content_type)
+		.header(header::CACHE_CONTROL, "max-age=86400")
+		.body(Cow::Owned(body))
+		.unwrap()
+}
+
+// Handler registered for `ztm-icon://`.
+pub fn serve_icon(ctx: UriSchemeContext<'_, tauri::Wry>, request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+	let app = ctx.app_handle();
+	let key = request.uri().path().trim_start_matches('/').to_string();
+	let mut stale = false;
+	let entry = with_index(app, |entries| {
+		let entry = entries.get_mut(&key)?;
+		stale = now().saturating_sub(entry.accessed_at) >= ACCESS_PRECISION.as_secs();
+		entry.accessed_at = now();
+		Some(entry.clone())
+	});
+	// keeps the LRU order across restarts without a write per request
+	if stale {
+		save_index(app);
+	}
+	let bytes = entry
+		.as_ref()
+		.and_then(|_| cache_dir(app).ok())
+		.filter(|_| key.chars().all(|c| c.is_ascii_hexdigit()))
+		.and_then(|dir| fs::read(dir.join(&key)).ok());
+	match (entry, bytes) {
+		(Some(entry), Some(bytes)) => respond(StatusCode::OK, &entry.content_type, bytes),
+		_ => respond(StatusCode::NOT_FOUND, "text/plain", b"icon not cached".to_vec()),
+	}
+}
+
+#[derive(Serialize)]
+pub struct FaviconCacheStats {
+	pub icons: usize,
+	pub bytes: u64,
+	pub referenced: usize,
+}
+
+#[command]
+pub async fn get_favicon_cache_stats(app: AppHandle) -> Result<FaviconCacheStats, String> {
+	let keep = referenced_keys(&app);
+	Ok(with_index(&app, |entries| FaviconCacheStats {
+		icons: entries.len(),
+		bytes: entries.values().map(|e| e.size).sum(),
+		referenced: entries.keys().filter(|k| keep.contains(*k)).count(),
+	}))
+}
+
+// Removes every cached icon no bookmark points to.
+#[command]
+pub async fn clear_favicon_cache(app: AppHandle) -> Result<usize, String> {
+	let keep = referenced_keys(&app);
+	with_index(&app, |entries| entries.retain(|key, _| keep.contains(key)));
+	let removed = evict(&app);
+	info!("removed {} cached favicons", removed);
+	Ok(removed)
+}
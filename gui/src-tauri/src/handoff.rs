@@ -0,0 +1,496 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Emitter;
+use tauri::Manager;
+use tauri::command;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use tauri_plugin_notification::NotificationExt;
+use log::{info, warn};
+
+use crate::proxy::{parse_proxy, ProxyScheme, ProxySpec};
+use crate::scope;
+use crate::store;
+use crate::tabs;
+
+// Opening a page in the system browser when the embedded webview cannot show it.
+// The mesh proxy is exposed on a fresh 127.0.0.1 port by a plain TCP forwarder,
+// and a Chromium-based browser or Firefox is started on a temporary profile that
+// uses it. The forwarder and the browser go away when the tab closes, when the
+// browser exits, on `close_handoff` or after the timeout, which `handoff-closed`
+// reports to the main window. The profile is removed once no browser holds its lock,
+// since the process started may only be a launcher. The `external-browser` store key
+// can point at the browser to use.
+const BROWSER_KEY: &str = "external-browser";
+pub const HANDOFF_CLOSED_EVENT: &str = "handoff-closed";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1800);
+const ACCEPT_POLL: Duration = Duration::from_millis(200);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// launchers that hand over to another process exit right away; only a later exit
+// means the browser was closed
+const LAUNCH_GRACE: Duration = Duration::from_secs(10);
+// how often the profile lock is looked at once the launcher is gone
+const LOCK_POLL: Duration = Duration::from_secs(2);
+// a profile still locked after this long is left for the next handoff to sweep
+const PROFILE_WAIT: Duration = Duration::from_secs(3600);
+const PROFILE_PREFIX: &str = "handoff-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserKind {
+	Chromium,
+	Firefox,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handoff {
+	pub id: u64,
+	// tab the page came from
+	pub label: Option<String>,
+	pub url: String,
+	pub browser: String,
+	pub kind: BrowserKind,
+	// local forwarder the browser uses, empty for direct pages
+	pub proxy: String,
+	pub upstream: String,
+	// unix seconds
+	pub expires_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HandoffClosed {
+	pub handoff: Handoff,
+	pub reason: String,
+}
+
+struct Running {
+	handoff: Handoff,
+	profile: PathBuf,
+	stop: Arc<AtomicBool>,
+}
+
+lazy_static! {
+	static ref HANDOFFS: Mutex<HashMap<u64, Running>> = Mutex::new(HashMap::new());
+}
+static NEXT_HANDOFF: AtomicU64 = AtomicU64::new(1);
+
+fn browser_kind(path: &Path) -> BrowserKind {
+	let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+	if name.contains("firefox") {
+		BrowserKind::Firefox
+	} else {
+		BrowserKind::Chromium
+	}
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn browser_candidates() -> Vec<PathBuf> {
+	const NAMES: &[&str] = &[
+		"google-chrome",
+		"google-chrome-stable",
+		"chromium",
+		"chromium-browser",
+		"microsoft-edge",
+		"brave-browser",
+		"firefox",
+	];
+	let dirs: Vec<PathBuf> = std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default();
+	NAMES.iter().flat_map(|name| dirs.iter().map(move |dir| dir.join(name))).collect()
+}
+
+#[cfg(target_os = "macos")]
+fn browser_candidates() -> Vec<PathBuf> {
+	[
+		"/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+		"/Applications/Chromium.app/Contents/MacOS/Chromium",
+		"/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+		"/Applications/Brave Browser.app/Contents/MacOS/Brave Browser",
+		"/Applications/Firefox.app/Contents/MacOS/firefox",
+	]
+	.iter()
+	.map(PathBuf::from)
+	.collect()
+}
+
+#[cfg(windows)]
+fn browser_candidates() -> Vec<PathBuf> {
+	const PATHS: &[&str] = &[
+		r"Google\Chrome\Application\chrome.exe",
+		r"Microsoft\Edge\Application\msedge.exe",
+		r"BraveSoftware\Brave-Browser\Application\brave.exe",
+		r"Mozilla Firefox\firefox.exe",
+	];
+	let roots: Vec<PathBuf> = ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+		.iter()
+		.filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+		.collect();
+	PATHS.iter().flat_map(|path| roots.iter().map(move |root| root.join(path))).collect()
+}
+
+fn find_browser(app: &AppHandle) -> Result<PathBuf, String> {
+	let configured = store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(BROWSER_KEY))
+		.and_then(|value| value.as_str().map(PathBuf::from))
+		.filter(|path| !path.as_os_str().is_empty());
+	if let Some(path) = configured {
+		return if path.is_file() { Ok(path) } else { Err(format!("Browser {} does not exist", path.display())) };
+	}
+	browser_candidates()
+		.into_iter()
+		.find(|path| path.is_file())
+		.ok_or_else(|| "No Chromium-based browser or Firefox found, set one in external-browser".to_string())
+}
+
+fn firefox_prefs(local: &ProxySpec) -> String {
+	let mut prefs = vec![
+		"user_pref(\"browser.shell.checkDefaultBrowser\", false);".to_string(),
+		"user_pref(\"browser.aboutwelcome.enabled\", false);".to_string(),
+		"user_pref(\"network.proxy.type\", 1);".to_string(),
+	];
+	match local.scheme {
+		ProxyScheme::Socks5 => {
+			prefs.push(format!("user_pref(\"network.proxy.socks\", \"{}\");", local.host));
+			prefs.push(format!("user_pref(\"network.proxy.socks_port\", {});", local.port));
+			prefs.push("user_pref(\"network.proxy.socks_version\", 5);".to_string());
+			prefs.push("user_pref(\"network.proxy.socks_remote_dns\", true);".to_string());
+		}
+		ProxyScheme::Http => {
+			for key in ["http", "ssl"] {
+				prefs.push(format!("user_pref(\"network.proxy.{}\", \"{}\");", key, local.host));
+				prefs.push(format!("user_pref(\"network.proxy.{}_port\", {});", key, local.port));
+			}
+		}
+	}
+	prefs.join("\n") + "\n"
+}
+
+fn launch(browser: &Path, profile: &Path, local: Option<&ProxySpec>, url: &str) -> io::Result<Child> {
+	std::fs::create_dir_all(profile)?;
+	let mut command = Command::new(browser);
+	match browser_kind(browser) {
+		BrowserKind::Chromium => {
+			command.arg(format!("--user-data-dir={}", profile.display()));
+			command.args(["--no-first-run", "--no-default-browser-check"]);
+			if let Some(local) = local {
+				command.arg(format!("--proxy-server={}", local.to_url().as_str().trim_end_matches('/')));
+			}
+		}
+		BrowserKind::Firefox => {
+			if let Some(local) = local {
+				std::fs::write(profile.join("user.js"), firefox_prefs(local))?;
+			}
+			command.arg("-no-remote").arg("-profile").arg(profile);
+		}
+	}
+	command.arg(url).spawn()
+}
+
+// Whether a browser still runs on `profile`. Chromium and Firefox on Linux keep a
+// symlink to `host-pid` or `address:+pid` there, which a killed browser leaves behind.
+#[cfg(unix)]
+fn profile_in_use(profile: &Path, kind: BrowserKind) -> bool {
+	let name = match kind {
+		BrowserKind::Chromium => "SingletonLock",
+		BrowserKind::Firefox => "lock",
+	};
+	let Ok(target) = std::fs::read_link(profile.join(name)) else {
+		return false;
+	};
+	let target = target.to_string_lossy().to_string();
+	let Ok(pid) = target.rsplit(['-', '+']).next().unwrap_or_default().parse::<u32>() else {
+		return true;
+	};
+	Command::new("kill")
+		.args(["-0", &pid.to_string()])
+		.stderr(Stdio::null())
+		.status()
+		.is_ok_and(|status| status.success())
+}
+
+#[cfg(windows)]
+fn profile_in_use(profile: &Path, kind: BrowserKind) -> bool {
+	match kind {
+		// deleted by the system once the browser is gone
+		BrowserKind::Chromium => profile.join("lockfile").exists(),
+		// held open while the browser runs, so it cannot be removed
+		BrowserKind::Firefox => {
+			let lock = profile.join("parent.lock");
+			lock.exists() && std::fs::remove_file(&lock).is_err()
+		}
+	}
+}
+
+#[cfg(not(any(unix, windows)))]
+fn profile_in_use(_profile: &Path, _kind: BrowserKind) -> bool {
+	false
+}
+
+// Removes `profile` once the browser using it is gone, waiting up to `PROFILE_WAIT`.
+fn remove_profile(profile: &Path, kind: BrowserKind) {
+	let waiting = Instant::now();
+	while profile_in_use(profile, kind) {
+		if waiting.elapsed() >= PROFILE_WAIT {
+			warn!("the browser profile {} is still in use, keeping it", profile.display());
+			return;
+		}
+		thread::sleep(LOCK_POLL);
+	}
+	if let Err(e) = std::fs::remove_dir_all(profile) {
+		warn!("failed to remove the browser profile {}: {}", profile.display(), e);
+	}
+}
+
+// Profiles of earlier handoffs whose browser has exited since.
+fn sweep_profiles(dir: &Path) {
+	let Ok(entries) = std::fs::read_dir(dir) else {
+		return;
+	};
+	let running: Vec<PathBuf> = HANDOFFS.lock().unwrap().values().map(|r| r.profile.clone()).collect();
+	for entry in entries.flatten() {
+		let path = entry.path();
+		let stale = entry.file_name().to_string_lossy().starts_with(PROFILE_PREFIX)
+			&& path.is_dir()
+			&& !running.contains(&path)
+			&& !profile_in_use(&path, BrowserKind::Chromium)
+			&& !profile_in_use(&path, BrowserKind::Firefox);
+		if stale && let Err(e) = std::fs::remove_dir_all(&path) {
+			warn!("failed to remove the browser profile {}: {}", path.display(), e);
+		}
+	}
+}
+
+fn pipe(client: TcpStream, upstream: &ProxySpec) -> io::Result<()> {
+	let addr = (upstream.host.as_str(), upstream.port)
+		.to_socket_addrs()?
+		.next()
+		.ok_or_else(|| io::Error::new(ErrorKind::NotFound, "proxy host has no address"))?;
+	let server = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+	let (mut client_read, mut server_write) = (client.try_clone()?, server.try_clone()?);
+	let upload = thread::spawn(move || {
+		let _ = io::copy(&mut client_read, &mut server_write);
+		let _ = server_write.shutdown(Shutdown::Write);
+	});
+	let (mut server_read, mut client_write) = (server, client);
+	let _ = io::copy(&mut server_read, &mut client_write);
+	let _ = client_write.shutdown(Shutdown::Both);
+	let _ = upload.join();
+	Ok(())
+}
+
+// What `serve` needs besides the connections.
+struct Session {
+	id: u64,
+	browser: Child,
+	kind: BrowserKind,
+	profile: PathBuf,
+	stop: Arc<AtomicBool>,
+	deadline: Instant,
+}
+
+// Accepts connections until the handoff stops, then tears everything down.
+fn serve(app: AppHandle, listener: Option<TcpListener>, upstream: Option<ProxySpec>, session: Session) {
+	let Session { id, mut browser, kind, profile, stop, deadline } = session;
+	let started = Instant::now();
+	let mut checked = Instant::now();
+	// a clone of each open connection, to cut it when the handoff ends
+	let mut clients: Vec<(TcpStream, thread::JoinHandle<()>)> = Vec::new();
+	let reason = loop {
+		clients.retain(|(_, piping)| !piping.is_finished());
+		if stop.load(Ordering::Relaxed) {
+			break "closed";
+		}
+		if Instant::now() >= deadline {
+			break "timed out";
+		}
+		// the launcher may have handed over to a browser that still uses the profile
+		if started.elapsed() >= LAUNCH_GRACE && checked.elapsed() >= LOCK_POLL {
+			checked = Instant::now();
+			if matches!(browser.try_wait(), Ok(Some(_))) && !profile_in_use(&profile, kind) {
+				break "browser exited";
+			}
+		}
+		let accepted = match &listener {
+			Some(listener) => listener.accept(),
+			None => Err(io::Error::from(ErrorKind::WouldBlock)),
+		};
+		match accepted {
+			Ok((client, _)) => {
+				let Some(upstream) = upstream.clone() else {
+					continue;
+				};
+				let clone = client.try_clone();
+				let piping = thread::spawn(move || {
+					if let Err(e) = pipe(client, &upstream) {
+						warn!("handoff {} could not reach {}: {}", id, upstream.authority(), e);
+					}
+				});
+				if let Ok(clone) = clone {
+					clients.push((clone, piping));
+				}
+			}
+			Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+			Err(e) => {
+				warn!("handoff {} listener failed: {}", id, e);
+				break "listener failed";
+			}
+		}
+	};
+	drop(listener);
+	for (client, _) in clients {
+		let _ = client.shutdown(Shutdown::Both);
+	}
+	let _ = browser.kill();
+	let _ = browser.wait();
+	let running = HANDOFFS.lock().unwrap().remove(&id);
+	info!("handoff {} {}", id, reason);
+	if let Some(running) = running {
+		notify_closed(&app, running.handoff, reason);
+	}
+	remove_profile(&profile, kind);
+}
+
+// Tells the main window, and the user when the page is cut off while still open.
+fn notify_closed(app: &AppHandle, handoff: Handoff, reason: &str) {
+	if reason == "timed out" {
+		let notice = app
+			.notification()
+			.builder()
+			.title("Page closed")
+			.body(format!("{} no longer goes through the mesh, its time is up.", handoff.url))
+			.show();
+		if let Err(e) = notice {
+			warn!("failed to notify the end of handoff {}: {}", handoff.id, e);
+		}
+	}
+	let closed = HandoffClosed { handoff, reason: reason.to_string() };
+	if let Err(e) = app.emit_to(scope::MAIN_WINDOW, HANDOFF_CLOSED_EVENT, closed) {
+		warn!("failed to emit {}: {}", HANDOFF_CLOSED_EVENT, e);
+	}
+}
+
+// Stops the handoffs started from tab `label`; called when the tab goes away.
+pub(crate) fn tab_closed(label: &str) {
+	for running in HANDOFFS.lock().unwrap().values() {
+		if running.handoff.label.as_deref() == Some(label) {
+			running.stop.store(true, Ordering::Relaxed);
+		}
+	}
+}
+
+// Opens the page of tab `label`, or `url`, in the system browser. The proxy is the
+// tab's (or `proxy`); pages without one open directly. `timeout` is in seconds.
+#[command]
+pub async fn open_in_system_browser(
+	app: AppHandle,
+	label: Option<String>,
+	url: Option<String>,
+	proxy: Option<String>,
+	timeout: Option<u64>,
+) -> Result<Handoff, String> {
+	if cfg!(any(target_os = "ios", target_os = "android")) {
+		return Err("Opening pages in another browser is not supported on this platform".to_string());
+	}
+	let tab = match &label {
+		Some(label) => Some(tabs::find_tab(label).ok_or_else(|| format!("No tab {}", label))?),
+		None => None,
+	};
+	let url = url
+		.or_else(|| tab.as_ref().map(|t| t.url.clone()))
+		.ok_or_else(|| "No URL to open".to_string())?;
+	let proxy = proxy
+		.or_else(|| tab.as_ref().map(|t| t.proxy.clone()))
+		.filter(|p| !p.is_empty())
+		.or_else(|| tab.as_ref().and_then(|t| tabs::mesh_proxy(&t.mesh)))
+		.unwrap_or_default();
+	let upstream = if proxy.is_empty() { None } else { Some(parse_proxy(&proxy).map_err(|e| e.to_string())?) };
+	let browser = find_browser(&app)?;
+
+	let listener = match &upstream {
+		Some(_) => {
+			let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+			listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+			Some(listener)
+		}
+		None => None,
+	};
+	let local = match (&listener, &upstream) {
+		(Some(listener), Some(upstream)) => {
+			let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+			Some(ProxySpec { scheme: upstream.scheme, host: "127.0.0.1".to_string(), port })
+		}
+		_ => None,
+	};
+
+	let id = NEXT_HANDOFF.fetch_add(1, Ordering::Relaxed);
+	let cache = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+	let kind = browser_kind(&browser);
+	sweep_profiles(&cache);
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+	// ids restart with the app while profiles of its earlier runs may still be in use
+	let profile = cache.join(format!("{}{}-{}", PROFILE_PREFIX, now, id));
+	let child = launch(&browser, &profile, local.as_ref(), &url)
+		.map_err(|e| format!("Failed to start {}: {}", browser.display(), e))?;
+	let timeout = timeout.map(Duration::from_secs).unwrap_or(DEFAULT_TIMEOUT);
+	let handoff = Handoff {
+		id,
+		label,
+		url,
+		browser: browser.to_string_lossy().to_string(),
+		kind,
+		proxy: local.as_ref().map(|l| l.to_url().to_string()).unwrap_or_default(),
+		upstream: proxy,
+		expires_at: now + timeout.as_secs(),
+	};
+	let stop = Arc::new(AtomicBool::new(false));
+	HANDOFFS.lock().unwrap().insert(id, Running { handoff: handoff.clone(), profile: profile.clone(), stop: stop.clone() });
+	let session = Session { id, browser: child, kind, profile, stop, deadline: Instant::now() + timeout };
+	thread::spawn(move || serve(app, listener, upstream, session));
+	let shown = handoff.label.as_deref().map_or(handoff.url.clone(), |label| tabs::loggable(label, &handoff.url));
+	info!("handoff {} opened {} in {}", id, shown, handoff.browser);
+	Ok(handoff)
+}
+
+#[command]
+pub async fn list_handoffs() -> Result<Vec<Handoff>, String> {
+	let mut handoffs: Vec<Handoff> = HANDOFFS.lock().unwrap().values().map(|r| r.handoff.clone()).collect();
+	handoffs.sort_by_key(|h| h.id);
+	Ok(handoffs)
+}
+
+#[command]
+pub async fn close_handoff(
+	id: u64,
+) -> Result<(), String> {
+	let handoffs = HANDOFFS.lock().unwrap();
+	let running = handoffs.get(&id).ok_or_else(|| format!("No handoff {}", id))?;
+	running.stop.store(true, Ordering::Relaxed);
+	Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn profile_locks_follow_the_process() {
+		let profile = std::env::temp_dir().join(format!("ztm-handoff-test-{}", std::process::id()));
+		std::fs::create_dir_all(&profile).unwrap();
+		assert!(!profile_in_use(&profile, BrowserKind::Chromium));
+		std::os::unix::fs::symlink(format!("host-{}", std::process::id()), profile.join("SingletonLock")).unwrap();
+		assert!(profile_in_use(&profile, BrowserKind::Chromium));
+		assert!(!profile_in_use(&profile, BrowserKind::Firefox));
+		std::os::unix::fs::symlink("127.0.0.1:+4194305", profile.join("lock")).unwrap();
+		assert!(!profile_in_use(&profile, BrowserKind::Firefox));
+		std::fs::remove_dir_all(&profile).unwrap();
+	}
+}
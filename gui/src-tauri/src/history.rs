@@ -0,0 +1,275 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::Webview;
+use tauri::command;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use log::warn;
+
+use crate::bookmarks;
+use crate::tabs;
+
+// Pages visited in browser webviews, one row per finished page load, in history.db
+// next to messages.db. Private tabs are never recorded. The retention settings cap
+// the number of rows and their age and are applied on every visit.
+pub const HISTORY_DB: &str = "history.db";
+const SCHEMA: &str = r#"
+	CREATE TABLE IF NOT EXISTS meta (
+		key TEXT PRIMARY KEY,
+		value TEXT NOT NULL
+	);
+	CREATE TABLE IF NOT EXISTS visits (
+		id INTEGER PRIMARY KEY,
+		url TEXT NOT NULL,
+		title TEXT NOT NULL DEFAULT '',
+		mesh TEXT NOT NULL DEFAULT '',
+		proxy TEXT NOT NULL DEFAULT '',
+		visited_at INTEGER NOT NULL
+	);
+	CREATE INDEX IF NOT EXISTS visits_time ON visits (visited_at);
+	CREATE INDEX IF NOT EXISTS visits_mesh ON visits (mesh, visited_at);
+"#;
+const DEFAULT_MAX_ENTRIES: i64 = 20000;
+const DEFAULT_MAX_AGE_DAYS: i64 = 90;
+
+lazy_static! {
+	static ref HISTORY: Mutex<Option<Connection>> = Mutex::new(None);
+	// label -> visit of the page it shows, for the title that arrives after the load
+	static ref CURRENT: Mutex<HashMap<String, i64>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+	pub id: i64,
+	pub url: String,
+	pub title: String,
+	pub mesh: String,
+	pub proxy: String,
+	// unix seconds
+	pub visited_at: i64,
+}
+
+// `None` disables a limit.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRetention {
+	pub max_entries: Option<i64>,
+	pub max_age_days: Option<i64>,
+}
+
+fn now() -> i64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn with_db<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+	let mut db = HISTORY.lock().unwrap();
+	let conn = db.as_mut().ok_or_else(|| "History store is not open".to_string())?;
+	f(conn).map_err(|e| e.to_string())
+}
+
+// A limit missing from `meta` has its default, an empty value disables it.
+fn read_limit(conn: &Connection, key: &str, default: i64) -> rusqlite::Result<Option<i64>> {
+	let value: Option<String> = conn
+		.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0))
+		.optional()?;
+	Ok(match value {
+		None => Some(default),
+		Some(value) => value.parse().ok(),
+	})
+}
+
+fn read_retention(conn: &Connection) -> rusqlite::Result<HistoryRetention> {
+	Ok(HistoryRetention {
+		max_entries: read_limit(conn, "max_entries", DEFAULT_MAX_ENTRIES)?,
+		max_age_days: read_limit(conn, "max_age_days", DEFAULT_MAX_AGE_DAYS)?,
+	})
+}
+
+fn apply_retention(conn: &Connection) -> rusqlite::Result<usize> {
+	let retention = read_retention(conn)?;
+	let mut removed = 0;
+	if let Some(days) = retention.max_age_days {
+		removed += conn.execute("DELETE FROM visits WHERE visited_at < ?1", params![now() - days * 86400])?;
+	}
+	if let Some(max) = retention.max_entries {
+		removed += conn.execute(
+			"DELETE FROM visits WHERE id <= (SELECT id FROM visits ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+			params![max],
+		)?;
+	}
+	Ok(removed)
+}
+
+// Opens (creating if needed) the history database.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+	let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+	std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	let conn = Connection::open(dir.join(HISTORY_DB)).map_err(|e| e.to_string())?;
+	conn.execute_batch("PRAGMA journal_mode = WAL;").map_err(|e| e.to_string())?;
+	conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+	if let Err(e) = apply_retention(&conn) {
+		warn!("history retention failed: {}", e);
+	}
+	*HISTORY.lock().unwrap() = Some(conn);
+	Ok(())
+}
+
+// Folds the WAL back into history.db so the file alone is a consistent copy.
+pub fn checkpoint() -> Result<(), String> {
+	with_db(|conn| conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);"))
+}
+
+// Checks a copy of history.db at `path`, e.g. one staged by a backup restore, and brings
+// its schema up to date.
+pub fn prepare_file(path: &Path) -> Result<(), String> {
+	let conn = Connection::open(path).map_err(|e| e.to_string())?;
+	let status: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+	if status != "ok" {
+		return Err(format!("history.db is damaged: {}", status));
+	}
+	conn.execute_batch(SCHEMA).map_err(|e| e.to_string())
+}
+
+// Releases the database file, e.g. before a backup restore replaces it.
+pub fn close() {
+	HISTORY.lock().unwrap().take();
+}
+
+// Called when webview `label` has finished loading `url`.
+pub(crate) fn record(label: &str, url: &Url) {
+	if url.scheme() != "http" && url.scheme() != "https" {
+		return;
+	}
+	let Some(tab) = tabs::find_tab(label).filter(|t| !t.incognito) else {
+		return;
+	};
+	let result = with_db(|conn| {
+		conn.execute(
+			"INSERT INTO visits (url, title, mesh, proxy, visited_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+			params![url.as_str(), tab.title, tab.mesh, tab.proxy, now()],
+		)?;
+		let id = conn.last_insert_rowid();
+		apply_retention(conn)?;
+		Ok(id)
+	});
+	match result {
+		Ok(id) => {
+			CURRENT.lock().unwrap().insert(label.to_string(), id);
+		}
+		Err(e) => warn!("failed to record the visit of {}: {}", url, e),
+	}
+}
+
+// Called when the document title of webview `label` changes.
+pub(crate) fn set_title(label: &str, title: &str) {
+	let Some(id) = CURRENT.lock().unwrap().get(label).copied() else {
+		return;
+	};
+	if let Err(e) = with_db(|conn| conn.execute("UPDATE visits SET title = ?1 WHERE id = ?2", params![title, id])) {
+		warn!("failed to update the visit title of {}: {}", label, e);
+	}
+}
+
+pub(crate) fn forget(label: &str) {
+	CURRENT.lock().unwrap().remove(label);
+}
+
+// Most recent visits first; `query` matches URLs and titles, `from`/`to` are unix
+// seconds. Browser pages only see the history of their own mesh.
+#[command]
+pub async fn search_history(
+	webview: Webview,
+	query: Option<String>,
+	mesh: Option<String>,
+	from: Option<i64>,
+	to: Option<i64>,
+	limit: Option<i64>,
+) -> Result<Vec<HistoryEntry>, String> {
+	search(bookmarks::requested_mesh(&webview, mesh), query, from, to, limit)
+}
+
+pub(crate) fn search(
+	mesh: Option<String>,
+	query: Option<String>,
+	from: Option<i64>,
+	to: Option<i64>,
+	limit: Option<i64>,
+) -> Result<Vec<HistoryEntry>, String> {
+	let pattern = query.filter(|q| !q.trim().is_empty()).map(|q| {
+		format!("%{}%", q.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+	});
+	with_db(|conn| {
+		let mut stmt = conn.prepare(
+			"SELECT id, url, title, mesh, proxy, visited_at FROM visits
+				WHERE (?1 IS NULL OR url LIKE ?1 ESCAPE '\\' OR title LIKE ?1 ESCAPE '\\')
+					AND (?2 IS NULL OR mesh = ?2)
+					AND (?3 IS NULL OR visited_at >= ?3) AND (?4 IS NULL OR visited_at < ?4)
+				ORDER BY visited_at DESC, id DESC LIMIT ?5",
+		)?;
+		let rows = stmt.query_map(params![pattern, mesh, from, to, limit.unwrap_or(50)], |row| {
+			Ok(HistoryEntry {
+				id: row.get(0)?,
+				url: row.get(1)?,
+				title: row.get(2)?,
+				mesh: row.get(3)?,
+				proxy: row.get(4)?,
+				visited_at: row.get(5)?,
+			})
+		})?;
+		rows.collect()
+	})
+}
+
+// Deletes the visits of `mesh` (all meshes without it) between `from` and `to`
+// (unix seconds, open-ended when missing). Returns the number of visits removed.
+#[command]
+pub async fn clear_history(
+	webview: Webview,
+	mesh: Option<String>,
+	from: Option<i64>,
+	to: Option<i64>,
+) -> Result<usize, String> {
+	clear(bookmarks::requested_mesh(&webview, mesh), from, to)
+}
+
+pub(crate) fn clear(mesh: Option<String>, from: Option<i64>, to: Option<i64>) -> Result<usize, String> {
+	with_db(|conn| {
+		conn.execute(
+			"DELETE FROM visits WHERE (?1 IS NULL OR mesh = ?1)
+				AND (?2 IS NULL OR visited_at >= ?2) AND (?3 IS NULL OR visited_at < ?3)",
+			params![mesh, from, to],
+		)
+	})
+}
+
+#[command]
+pub async fn get_history_retention() -> Result<HistoryRetention, String> {
+	with_db(|conn| read_retention(conn))
+}
+
+// Saves the limits and applies them right away; returns the number of visits removed.
+#[command]
+pub async fn set_history_retention(retention: HistoryRetention) -> Result<usize, String> {
+	if retention.max_entries.is_some_and(|n| n < 1) || retention.max_age_days.is_some_and(|d| d < 1) {
+		return Err("History limits must be positive".to_string());
+	}
+	with_db(|conn| {
+		let tx = conn.transaction()?;
+		for (key, value) in [("max_entries", retention.max_entries), ("max_age_days", retention.max_age_days)] {
+			tx.execute(
+				"INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+				params![key, value.map(|v| v.to_string()).unwrap_or_default()],
+			)?;
+		}
+		let removed = apply_retention(&tx)?;
+		tx.commit()?;
+		Ok(removed)
+	})
+}
@@ -19,7 +19,29 @@ use log::{trace, debug, info, warn, error};
 mod binary;
 mod pay;
 mod browser;
+mod embedded;
+mod proxy;
+mod routing;
+mod navigation;
+mod profiles;
+mod downloads;
+mod bookmarks;
+mod favicons;
+mod toolbar;
+mod scope;
+mod tabs;
+mod session;
+mod agent;
+mod tunnels;
+mod certs;
+mod sites;
+mod telemetry;
+mod history;
+mod pdf;
+mod handoff;
 mod store;
+mod backup;
+mod messages;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -41,19 +63,121 @@ pub fn run() {
 				.plugin(tauri_plugin_log::Builder::new().targets([
             Target::new(TargetKind::Stdout),
             Target::new(TargetKind::LogDir { file_name: None }),
-            Target::new(TargetKind::Webview),
+            // no Webview target: it emits every line to all webviews, browser pages included
         ]).build())
 				// .plugin(tauri_plugin_sharesheet::init())
-				.invoke_handler(tauri::generate_handler![
+				.register_uri_scheme_protocol(favicons::ICON_SCHEME, favicons::serve_icon)
+				.register_asynchronous_uri_scheme_protocol(tunnels::ZTM_SCHEME, tunnels::serve_link)
+				.on_page_load(toolbar::on_page_load)
+				.setup(|app| {
+					if let Err(e) = store::run_migrations(app.handle()) {
+						error!("store migration failed: {}", e);
+					}
+					if let Err(e) = messages::init(app.handle()) {
+						error!("message store unavailable: {}", e);
+					}
+					if let Err(e) = history::init(app.handle()) {
+						error!("history store unavailable: {}", e);
+					}
+					downloads::init(app.handle());
+					session::init(app.handle());
+					tunnels::init(app.handle());
+					Ok(())
+				})
+				.invoke_handler(scope::guard(tauri::generate_handler![
 					binary::pipylib,
 					binary::create_private_key,
 					browser::create_proxy_webview,
-					browser::create_wry_webview,
+					embedded::create_wry_webview,
+					embedded::list_wry_webviews,
+					embedded::set_wry_webview_bounds,
+					embedded::set_wry_webview_visible,
+					embedded::focus_wry_webview,
+					embedded::reload_wry_webview,
+					embedded::destroy_wry_webview,
+					tabs::open_tab,
+					tabs::close_tab,
+					tabs::activate_tab,
+					tabs::reorder_tab,
+					tabs::list_tabs,
+					session::get_browser_session,
+					session::restore_browser_session,
+					session::discard_browser_session,
+					routing::get_proxy_rules,
+					routing::set_proxy_rules,
+					routing::test_proxy_route,
+					navigation::get_navigation_policy,
+					navigation::set_navigation_policy,
+					agent::set_agent_port,
+					certs::list_cert_exceptions,
+					certs::remove_cert_exception,
+					certs::get_mesh_ca_trust,
+					certs::set_mesh_ca_trust,
+					certs::get_mesh_ca,
+					sites::list_site_settings,
+					sites::set_site_settings,
+					sites::remove_site_settings,
+					telemetry::list_page_loads,
+					telemetry::clear_page_loads,
+					history::search_history,
+					history::clear_history,
+					history::get_history_retention,
+					history::set_history_retention,
+					pdf::print_to_pdf,
+					handoff::open_in_system_browser,
+					handoff::list_handoffs,
+					handoff::close_handoff,
+					navigation::list_navigation_requests,
+					navigation::respond_navigation,
+					profiles::list_browser_profiles,
+					profiles::clear_browser_profiles,
+					downloads::list_downloads,
+					downloads::retry_download,
+					downloads::open_download_folder,
+					downloads::clear_downloads,
+					downloads::get_download_settings,
+					downloads::set_download_settings,
+					bookmarks::get_bookmark_tree,
+					bookmarks::list_bookmarks,
+					bookmarks::find_bookmark,
+					bookmarks::add_bookmark,
+					bookmarks::update_bookmark,
+					bookmarks::remove_bookmark,
+					bookmarks::move_bookmark,
+					bookmarks::create_bookmark_folder,
+					bookmarks::rename_bookmark_folder,
+					bookmarks::remove_bookmark_folder,
+					bookmarks::import_bookmarks,
+					bookmarks::export_bookmarks,
+					favicons::get_favicon_cache_stats,
+					favicons::clear_favicon_cache,
+					toolbar::toolbar_navigate,
+					toolbar::toolbar_back,
+					toolbar::toolbar_forward,
+					toolbar::toolbar_reload,
+					toolbar::toolbar_bookmark,
+					toolbar::toolbar_history_menu,
 					pay::purchase_product,
 					store::push_store_list,
 					store::get_store_list,
-					store::set_store_list
-				])
+					store::set_store_list,
+					store::query_store_list,
+					store::count_store_list,
+					store::subscribe_store_changes,
+					store::unsubscribe_store_changes,
+					backup::export_backup,
+					backup::inspect_backup,
+					backup::import_backup,
+					messages::append_message,
+					messages::sync_messages,
+					messages::list_messages,
+					messages::count_messages,
+					messages::search_messages,
+					messages::clear_messages,
+					messages::set_retention_policy,
+					messages::get_retention_policies,
+					messages::export_messages
+				]))
 				.run(tauri::generate_context!())
 				.expect("error while running tauri application");
 }
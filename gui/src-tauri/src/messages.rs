@@ -0,0 +1,690 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use log::{info, warn};
+
+use crate::store;
+
+// Chat and bot conversations used to live in store.json as whole JSON arrays
+// (`bot-history-{mesh}-{room}`, `bot-content-{mesh}-{room}`). They are kept here
+// instead: one conversation row per (mesh, room, kind), messages appended one at a
+// time, and an FTS5 index over their text.
+pub const MESSAGES_DB: &str = "messages.db";
+const SCHEMA: &str = r#"
+	CREATE TABLE IF NOT EXISTS meta (
+		key TEXT PRIMARY KEY,
+		value TEXT NOT NULL
+	);
+	CREATE TABLE IF NOT EXISTS conversations (
+		id INTEGER PRIMARY KEY,
+		mesh TEXT NOT NULL,
+		room TEXT NOT NULL,
+		kind TEXT NOT NULL,
+		UNIQUE (mesh, room, kind)
+	);
+	CREATE TABLE IF NOT EXISTS messages (
+		id INTEGER PRIMARY KEY,
+		conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+		created_at INTEGER NOT NULL,
+		role TEXT NOT NULL DEFAULT '',
+		content TEXT NOT NULL DEFAULT '',
+		body TEXT NOT NULL
+	);
+	CREATE INDEX IF NOT EXISTS messages_conversation ON messages (conversation_id, id);
+	CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5 (
+		content, content='messages', content_rowid='id'
+	);
+	CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
+		INSERT INTO messages_fts (rowid, content) VALUES (new.id, new.content);
+	END;
+	CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
+		INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.id, old.content);
+	END;
+	CREATE TABLE IF NOT EXISTS retention_policies (
+		mesh TEXT NOT NULL,
+		room TEXT NOT NULL DEFAULT '',
+		max_messages INTEGER,
+		max_age_days INTEGER,
+		PRIMARY KEY (mesh, room)
+	);
+"#;
+// store.json key prefixes imported by `import_store_history`, with the kind they map to
+const STORE_KINDS: &[(&str, &str)] = &[("bot-history-", "history"), ("bot-content-", "content")];
+
+lazy_static! {
+	static ref MESSAGES: Mutex<Option<Connection>> = Mutex::new(None);
+}
+
+#[derive(Serialize)]
+pub struct StoredMessage {
+	pub id: i64,
+	pub created_at: i64,
+	pub role: String,
+	pub message: JsonValue,
+}
+
+#[derive(Serialize)]
+pub struct MessageHit {
+	pub id: i64,
+	pub mesh: String,
+	pub room: String,
+	pub kind: String,
+	pub created_at: i64,
+	pub snippet: String,
+	pub message: JsonValue,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+	pub mesh: String,
+	#[serde(default)]
+	pub room: String,
+	pub max_messages: Option<i64>,
+	pub max_age_days: Option<i64>,
+}
+
+fn now() -> i64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn with_db<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+	let mut db = MESSAGES.lock().unwrap();
+	let conn = db.as_mut().ok_or_else(|| "Message store is not open".to_string())?;
+	f(conn).map_err(|e| e.to_string())
+}
+
+// Text indexed for search: LLM messages carry `content`, chat bubbles carry `html`.
+fn searchable_text(message: &JsonValue) -> String {
+	if let Some(text) = ["content", "text", "message"].iter().find_map(|k| message.get(*k).and_then(|v| v.as_str())) {
+		return text.to_string();
+	}
+	if let Some(html) = message.get("html").and_then(|v| v.as_str()) {
+		let mut text = String::with_capacity(html.len());
+		let mut in_tag = false;
+		for c in html.chars() {
+			match c {
+				'<' => in_tag = true,
+				'>' => {
+					in_tag = false;
+					text.push(' ');
+				}
+				_ if !in_tag => text.push(c),
+				_ => {}
+			}
+		}
+		return text.split_whitespace().collect::<Vec<_>>().join(" ");
+	}
+	message.to_string()
+}
+
+fn conversation_id(conn: &Connection, mesh: &str, room: &str, kind: &str) -> rusqlite::Result<i64> {
+	conn.execute(
+		"INSERT OR IGNORE INTO conversations (mesh, room, kind) VALUES (?1, ?2, ?3)",
+		params![mesh, room, kind],
+	)?;
+	conn.query_row(
+		"SELECT id FROM conversations WHERE mesh = ?1 AND room = ?2 AND kind = ?3",
+		params![mesh, room, kind],
+		|row| row.get(0),
+	)
+}
+
+fn insert_message(conn: &Connection, conversation: i64, created_at: i64, message: &JsonValue) -> rusqlite::Result<i64> {
+	let role = message.get("role").and_then(|v| v.as_str()).unwrap_or("");
+	conn.execute(
+		"INSERT INTO messages (conversation_id, created_at, role, content, body) VALUES (?1, ?2, ?3, ?4, ?5)",
+		params![conversation, created_at, role, searchable_text(message), message.to_string()],
+	)?;
+	Ok(conn.last_insert_rowid())
+}
+
+// A room policy overrides the mesh-wide one (room = '').
+fn effective_policy(conn: &Connection, mesh: &str, room: &str) -> rusqlite::Result<Option<(Option<i64>, Option<i64>)>> {
+	conn.query_row(
+		"SELECT max_messages, max_age_days FROM retention_policies
+			WHERE mesh = ?1 AND (room = ?2 OR room = '') ORDER BY room DESC LIMIT 1",
+		params![mesh, room],
+		|row| Ok((row.get(0)?, row.get(1)?)),
+	).optional()
+}
+
+fn apply_retention(conn: &Connection, conversation: i64, mesh: &str, room: &str) -> rusqlite::Result<usize> {
+	let Some((max_messages, max_age_days)) = effective_policy(conn, mesh, room)? else {
+		return Ok(0);
+	};
+	let mut removed = 0;
+	if let Some(days) = max_age_days {
+		removed += conn.execute(
+			"DELETE FROM messages WHERE conversation_id = ?1 AND created_at < ?2",
+			params![conversation, now() - days * 86400],
+		)?;
+	}
+	if let Some(max) = max_messages {
+		removed += conn.execute(
+			"DELETE FROM messages WHERE conversation_id = ?1 AND id NOT IN
+				(SELECT id FROM messages WHERE conversation_id = ?1 ORDER BY id DESC LIMIT ?2)",
+			params![conversation, max],
+		)?;
+	}
+	Ok(removed)
+}
+
+fn apply_all_retention(conn: &Connection) -> rusqlite::Result<usize> {
+	let conversations: Vec<(i64, String, String)> = conn
+		.prepare("SELECT id, mesh, room FROM conversations")?
+		.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+		.collect::<rusqlite::Result<_>>()?;
+	let mut removed = 0;
+	for (id, mesh, room) in conversations {
+		removed += apply_retention(conn, id, &mesh, &room)?;
+	}
+	Ok(removed)
+}
+
+// A store.json key holding a whole conversation, with the conversation it maps to.
+struct LegacySource {
+	key: String,
+	mesh: String,
+	room: String,
+	kind: &'static str,
+	// last activity of the room, unix seconds
+	room_time: Option<i64>,
+}
+
+// Unix seconds from a JSON number in seconds or milliseconds.
+fn json_time(value: Option<&JsonValue>) -> Option<i64> {
+	let time = value?.as_i64()?;
+	Some(if time > 100_000_000_000 { time / 1000 } else { time })
+}
+
+// Legacy messages carry no time of their own unless the UI added one; otherwise the
+// last activity of their room is the closest known time.
+fn legacy_time(message: &JsonValue, source: &LegacySource) -> i64 {
+	["created_at", "time", "date", "timestamp"]
+		.iter()
+		.find_map(|k| json_time(message.get(*k)))
+		.or(source.room_time)
+		.unwrap_or_else(now)
+}
+
+fn entry_list(entries: &HashMap<String, JsonValue>, key: &str) -> Vec<JsonValue> {
+	entries.get(key).and_then(|json| json.as_array().cloned()).unwrap_or_default()
+}
+
+// Room ids are taken from `bot-rooms-{mesh}` since both mesh names and room ids may
+// contain '-'.
+fn legacy_sources(entries: &HashMap<String, JsonValue>) -> Vec<LegacySource> {
+	let mut sources = Vec::new();
+	for key in entries.keys() {
+		let Some(mesh) = key.strip_prefix("bot-rooms-") else {
+			continue;
+		};
+		let mut rooms = vec![(String::new(), None)];
+		rooms.extend(entry_list(entries, key).iter().filter_map(|r| {
+			let id = match r.get("id")? {
+				JsonValue::String(id) => id.clone(),
+				other => other.to_string(),
+			};
+			Some((id, json_time(r.get("time"))))
+		}));
+		for (room, room_time) in rooms {
+			for (prefix, kind) in STORE_KINDS {
+				let key = if room.is_empty() { format!("{}{}", prefix, mesh) } else { format!("{}{}-{}", prefix, mesh, room) };
+				if entries.contains_key(&key) {
+					sources.push(LegacySource { key, mesh: mesh.to_string(), room: room.clone(), kind, room_time });
+				}
+			}
+		}
+	}
+	sources
+}
+
+// Imports the legacy conversations found in `entries` in one transaction. Each key
+// is marked in `meta` once imported; keys whose room cannot be resolved yet are left
+// for a later launch. Returns the number of messages imported and every key that is
+// in the database by now, including ones imported by an earlier launch.
+fn import_entries(conn: &mut Connection, entries: &HashMap<String, JsonValue>) -> rusqlite::Result<(usize, Vec<String>)> {
+	let tx = conn.transaction()?;
+	let mut count = 0;
+	let mut keys = Vec::new();
+	for source in legacy_sources(entries) {
+		let marker = format!("imported:{}", source.key);
+		let imported = tx.query_row("SELECT 1 FROM meta WHERE key = ?1", params![marker], |_| Ok(())).optional()?;
+		if imported.is_none() {
+			let conversation = conversation_id(&tx, &source.mesh, &source.room, source.kind)?;
+			for message in entry_list(entries, &source.key) {
+				insert_message(&tx, conversation, legacy_time(&message, &source), &message)?;
+				count += 1;
+			}
+			tx.execute("INSERT INTO meta (key, value) VALUES (?1, ?2)", params![marker, now().to_string()])?;
+		}
+		keys.push(source.key);
+	}
+	tx.commit()?;
+	Ok((count, keys))
+}
+
+// Moves the conversations still kept as JSON arrays in store.json into the database
+// and drops their keys from store.json once the import has committed.
+fn import_store_history(app: &AppHandle, conn: &mut Connection) -> Result<usize, String> {
+	let store = store::open_store(app)?;
+	let entries: HashMap<String, JsonValue> = store.entries().into_iter().collect();
+	let (count, keys) = import_entries(conn, &entries).map_err(|e| e.to_string())?;
+	if keys.is_empty() {
+		return Ok(count);
+	}
+	for key in &keys {
+		store.delete(key);
+		store::notify_store_change(app, key, "delete");
+	}
+	store.save().map_err(|e| e.to_string())?;
+	info!("imported {} messages, removed {} store keys", count, keys.len());
+	Ok(count)
+}
+
+// Opens (creating if needed) the message database and imports legacy store keys.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+	let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+	std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	let mut conn = Connection::open(dir.join(MESSAGES_DB)).map_err(|e| e.to_string())?;
+	conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;").map_err(|e| e.to_string())?;
+	conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+	if let Err(e) = import_store_history(app, &mut conn) {
+		warn!("message store import failed: {}", e);
+	}
+	if let Err(e) = apply_all_retention(&conn) {
+		warn!("message retention failed: {}", e);
+	}
+	*MESSAGES.lock().unwrap() = Some(conn);
+	Ok(())
+}
+
+// Folds the WAL back into messages.db so the file alone is a consistent copy.
+pub fn checkpoint() -> Result<(), String> {
+	with_db(|conn| conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);"))
+}
+
+// Checks a copy of messages.db at `path`, e.g. one staged by a backup restore, and brings
+// its schema up to date.
+pub fn prepare_file(path: &Path) -> Result<(), String> {
+	let conn = Connection::open(path).map_err(|e| e.to_string())?;
+	let status: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+	if status != "ok" {
+		return Err(format!("messages.db is damaged: {}", status));
+	}
+	conn.execute_batch(SCHEMA).map_err(|e| e.to_string())
+}
+
+// Releases the database file, e.g. before a backup restore replaces it.
+pub fn close() {
+	MESSAGES.lock().unwrap().take();
+}
+
+#[command]
+pub async fn append_message(
+	mesh: String,
+	room: Option<String>,
+	kind: String,
+	message: JsonValue,
+) -> Result<i64, String> {
+	let room = room.unwrap_or_default();
+	with_db(|conn| {
+		let tx = conn.transaction()?;
+		let conversation = conversation_id(&tx, &mesh, &room, &kind)?;
+		let id = insert_message(&tx, conversation, now(), &message)?;
+		apply_retention(&tx, conversation, &mesh, &room)?;
+		tx.commit()?;
+		Ok(id)
+	})
+}
+
+// Makes the conversation end with `messages`, appending the ones it does not end
+// with yet. Callers keeping a sliding window, such as the LLM context, pass the
+// whole window; messages that fell out of it stay stored. Returns the number appended.
+fn sync_conversation(conn: &mut Connection, mesh: &str, room: &str, kind: &str, messages: &[JsonValue]) -> rusqlite::Result<usize> {
+	let bodies: Vec<String> = messages.iter().map(|m| m.to_string()).collect();
+	let tx = conn.transaction()?;
+	let conversation = conversation_id(&tx, mesh, room, kind)?;
+	let mut stored: Vec<String> = tx
+		.prepare("SELECT body FROM messages WHERE conversation_id = ?1 ORDER BY id DESC LIMIT ?2")?
+		.query_map(params![conversation, bodies.len() as i64], |row| row.get(0))?
+		.collect::<rusqlite::Result<_>>()?;
+	stored.reverse();
+	// longest tail of the stored messages the window starts with
+	let overlap = (0..=stored.len())
+		.rev()
+		.find(|&n| stored[stored.len() - n..] == bodies[..n])
+		.unwrap_or(0);
+	let created_at = now();
+	for message in &messages[overlap..] {
+		insert_message(&tx, conversation, created_at, message)?;
+	}
+	apply_retention(&tx, conversation, mesh, room)?;
+	tx.commit()?;
+	Ok(messages.len() - overlap)
+}
+
+#[command]
+pub async fn sync_messages(
+	mesh: String,
+	room: Option<String>,
+	kind: String,
+	messages: Vec<JsonValue>,
+) -> Result<usize, String> {
+	let room = room.unwrap_or_default();
+	with_db(|conn| sync_conversation(conn, &mesh, &room, &kind, &messages))
+}
+
+// Newest first; pass `beforeId` from the last page to keep paging stable while
+// new messages arrive.
+#[command]
+pub async fn list_messages(
+	mesh: String,
+	room: Option<String>,
+	kind: String,
+	before_id: Option<i64>,
+	limit: Option<i64>,
+) -> Result<Vec<StoredMessage>, String> {
+	let room = room.unwrap_or_default();
+	with_db(|conn| {
+		let mut stmt = conn.prepare(
+			"SELECT m.id, m.created_at, m.role, m.body FROM messages m
+				JOIN conversations c ON c.id = m.conversation_id
+				WHERE c.mesh = ?1 AND c.room = ?2 AND c.kind = ?3 AND m.id < ?4
+				ORDER BY m.id DESC LIMIT ?5",
+		)?;
+		let rows = stmt.query_map(
+			params![mesh, room, kind, before_id.unwrap_or(i64::MAX), limit.unwrap_or(50)],
+			|row| {
+				let body: String = row.get(3)?;
+				Ok(StoredMessage {
+					id: row.get(0)?,
+					created_at: row.get(1)?,
+					role: row.get(2)?,
+					message: serde_json::from_str(&body).unwrap_or(JsonValue::String(body)),
+				})
+			},
+		)?;
+		rows.collect()
+	})
+}
+
+#[command]
+pub async fn count_messages(
+	mesh: String,
+	room: Option<String>,
+	kind: String,
+) -> Result<i64, String> {
+	let room = room.unwrap_or_default();
+	with_db(|conn| {
+		conn.query_row(
+			"SELECT COUNT(*) FROM messages m JOIN conversations c ON c.id = m.conversation_id
+				WHERE c.mesh = ?1 AND c.room = ?2 AND c.kind = ?3",
+			params![mesh, room, kind],
+			|row| row.get(0),
+		)
+	})
+}
+
+// Full-text search using FTS5 query syntax, optionally narrowed to a mesh/room.
+#[command]
+pub async fn search_messages(
+	query: String,
+	mesh: Option<String>,
+	room: Option<String>,
+	limit: Option<i64>,
+) -> Result<Vec<MessageHit>, String> {
+	with_db(|conn| {
+		let mut stmt = conn.prepare(
+			"SELECT m.id, c.mesh, c.room, c.kind, m.created_at,
+					snippet(messages_fts, 0, '<b>', '</b>', '…', 12), m.body
+				FROM messages_fts JOIN messages m ON m.id = messages_fts.rowid
+				JOIN conversations c ON c.id = m.conversation_id
+				WHERE messages_fts MATCH ?1
+					AND (?2 IS NULL OR c.mesh = ?2) AND (?3 IS NULL OR c.room = ?3)
+				ORDER BY rank LIMIT ?4",
+		)?;
+		let rows = stmt.query_map(params![query, mesh, room, limit.unwrap_or(50)], |row| {
+			let body: String = row.get(6)?;
+			Ok(MessageHit {
+				id: row.get(0)?,
+				mesh: row.get(1)?,
+				room: row.get(2)?,
+				kind: row.get(3)?,
+				created_at: row.get(4)?,
+				snippet: row.get(5)?,
+				message: serde_json::from_str(&body).unwrap_or(JsonValue::String(body)),
+			})
+		})?;
+		rows.collect()
+	})
+}
+
+// Deletes a conversation; without `kind` every kind of the room goes.
+#[command]
+pub async fn clear_messages(
+	mesh: String,
+	room: Option<String>,
+	kind: Option<String>,
+) -> Result<usize, String> {
+	let room = room.unwrap_or_default();
+	with_db(|conn| {
+		let tx = conn.transaction()?;
+		let removed = tx.execute(
+			"DELETE FROM messages WHERE conversation_id IN
+				(SELECT id FROM conversations WHERE mesh = ?1 AND room = ?2 AND (?3 IS NULL OR kind = ?3))",
+			params![mesh, room, kind],
+		)?;
+		tx.execute(
+			"DELETE FROM conversations WHERE mesh = ?1 AND room = ?2 AND (?3 IS NULL OR kind = ?3)",
+			params![mesh, room, kind],
+		)?;
+		tx.commit()?;
+		Ok(removed)
+	})
+}
+
+#[command]
+pub async fn set_retention_policy(policy: RetentionPolicy) -> Result<usize, String> {
+	with_db(|conn| {
+		if policy.max_messages.is_none() && policy.max_age_days.is_none() {
+			conn.execute(
+				"DELETE FROM retention_policies WHERE mesh = ?1 AND room = ?2",
+				params![policy.mesh, policy.room],
+			)?;
+			return Ok(0);
+		}
+		conn.execute(
+			"INSERT OR REPLACE INTO retention_policies (mesh, room, max_messages, max_age_days) VALUES (?1, ?2, ?3, ?4)",
+			params![policy.mesh, policy.room, policy.max_messages, policy.max_age_days],
+		)?;
+		apply_all_retention(conn)
+	})
+}
+
+#[command]
+pub async fn get_retention_policies(mesh: String) -> Result<Vec<RetentionPolicy>, String> {
+	with_db(|conn| {
+		let mut stmt = conn.prepare(
+			"SELECT mesh, room, max_messages, max_age_days FROM retention_policies WHERE mesh = ?1",
+		)?;
+		let rows = stmt.query_map(params![mesh], |row| {
+			Ok(RetentionPolicy {
+				mesh: row.get(0)?,
+				room: row.get(1)?,
+				max_messages: row.get(2)?,
+				max_age_days: row.get(3)?,
+			})
+		})?;
+		rows.collect()
+	})
+}
+
+// Writes a conversation as JSON lines, oldest first, row by row so large
+// histories never have to be held in memory.
+#[command]
+pub async fn export_messages(
+	mesh: String,
+	room: Option<String>,
+	kind: String,
+	path: String,
+) -> Result<usize, String> {
+	let room = room.unwrap_or_default();
+	let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+	let mut out = BufWriter::new(file);
+	let count = with_db(|conn| {
+		let mut stmt = conn.prepare(
+			"SELECT m.id, m.created_at, m.role, m.body FROM messages m
+				JOIN conversations c ON c.id = m.conversation_id
+				WHERE c.mesh = ?1 AND c.room = ?2 AND c.kind = ?3 ORDER BY m.id",
+		)?;
+		let mut rows = stmt.query(params![mesh, room, kind])?;
+		let mut count = 0;
+		while let Some(row) = rows.next()? {
+			let body: String = row.get(3)?;
+			let line = serde_json::json!({
+				"id": row.get::<_, i64>(0)?,
+				"created_at": row.get::<_, i64>(1)?,
+				"role": row.get::<_, String>(2)?,
+				"message": serde_json::from_str::<JsonValue>(&body).unwrap_or(JsonValue::String(body)),
+			});
+			writeln!(out, "{}", line).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+			count += 1;
+		}
+		Ok(count)
+	})?;
+	out.flush().map_err(|e| e.to_string())?;
+	Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn open() -> Connection {
+		let conn = Connection::open_in_memory().unwrap();
+		conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+		conn.execute_batch(SCHEMA).unwrap();
+		conn
+	}
+
+	fn bodies(conn: &Connection, mesh: &str, room: &str, kind: &str) -> Vec<JsonValue> {
+		conn.prepare(
+			"SELECT m.body FROM messages m JOIN conversations c ON c.id = m.conversation_id
+				WHERE c.mesh = ?1 AND c.room = ?2 AND c.kind = ?3 ORDER BY m.id",
+		)
+		.unwrap()
+		.query_map(params![mesh, room, kind], |row| row.get::<_, String>(0))
+		.unwrap()
+		.map(|body| serde_json::from_str(&body.unwrap()).unwrap())
+		.collect()
+	}
+
+	#[test]
+	fn sync_appends_only_what_the_window_adds() {
+		let mut conn = open();
+		let a = json!({ "role": "user", "content": "a" });
+		let b = json!({ "role": "assistant", "content": "b" });
+		let c = json!({ "role": "user", "content": "c" });
+		let d = json!({ "role": "assistant", "content": "d" });
+		assert_eq!(sync_conversation(&mut conn, "m", "", "history", &[a.clone(), b.clone()]).unwrap(), 2);
+		// same window again adds nothing
+		assert_eq!(sync_conversation(&mut conn, "m", "", "history", &[a.clone(), b.clone()]).unwrap(), 0);
+		// the window slid: `a` fell out, `c` and `d` are new
+		assert_eq!(sync_conversation(&mut conn, "m", "", "history", &[b.clone(), c.clone(), d.clone()]).unwrap(), 2);
+		assert_eq!(bodies(&conn, "m", "", "history"), vec![a.clone(), b.clone(), c.clone(), d.clone()]);
+		// a window that does not continue the conversation is appended whole
+		assert_eq!(sync_conversation(&mut conn, "m", "", "history", &[a.clone(), c.clone()]).unwrap(), 2);
+		assert_eq!(bodies(&conn, "m", "", "history").len(), 6);
+		// an empty window is a no-op
+		assert_eq!(sync_conversation(&mut conn, "m", "", "history", &[]).unwrap(), 0);
+	}
+
+	#[test]
+	fn searchable_text_prefers_content_and_strips_html() {
+		assert_eq!(searchable_text(&json!({ "content": "hello", "html": "<b>x</b>" })), "hello");
+		assert_eq!(searchable_text(&json!({ "html": "<p>Hello <b>world</b></p>\n<br/>again" })), "Hello world again");
+		assert_eq!(searchable_text(&json!({ "id": 1 })), r#"{"id":1}"#);
+	}
+
+	#[test]
+	fn legacy_sources_resolve_rooms_and_times() {
+		let entries = HashMap::from([
+			("bot-rooms-my-mesh".to_string(), json!([{ "id": "r-1", "time": 1_700_000_000_000i64 }, { "id": 7 }])),
+			("bot-history-my-mesh".to_string(), json!([{ "content": "top" }])),
+			("bot-content-my-mesh-r-1".to_string(), json!([{ "content": "in r-1", "time": 1_600_000_000 }, { "content": "no time" }])),
+			("bot-history-my-mesh-7".to_string(), json!([])),
+			("bot-history-other-x".to_string(), json!([{ "content": "orphan" }])),
+		]);
+		let mut sources = legacy_sources(&entries);
+		sources.sort_by(|a, b| a.key.cmp(&b.key));
+		let keys: Vec<(&str, &str, &str)> = sources.iter().map(|s| (s.key.as_str(), s.room.as_str(), s.kind)).collect();
+		assert_eq!(keys, vec![
+			("bot-content-my-mesh-r-1", "r-1", "content"),
+			("bot-history-my-mesh", "", "history"),
+			("bot-history-my-mesh-7", "7", "history"),
+		]);
+		assert!(sources.iter().all(|s| s.mesh == "my-mesh"));
+
+		let room = &sources[0];
+		assert_eq!(room.room_time, Some(1_700_000_000));
+		let messages = entry_list(&entries, &room.key);
+		assert_eq!(legacy_time(&messages[0], room), 1_600_000_000);
+		assert_eq!(legacy_time(&messages[1], room), 1_700_000_000);
+		let top = &sources[1];
+		let before = now();
+		assert!(legacy_time(&json!({ "content": "top" }), top) >= before);
+	}
+
+	#[test]
+	fn import_is_recorded_once() {
+		let mut conn = open();
+		let mut entries = HashMap::from([
+			("bot-rooms-m".to_string(), json!([{ "id": "r" }])),
+			("bot-history-m-r".to_string(), json!([{ "content": "one" }, { "content": "two" }])),
+		]);
+		let (count, keys) = import_entries(&mut conn, &entries).unwrap();
+		assert_eq!((count, keys.clone()), (2, vec!["bot-history-m-r".to_string()]));
+		assert_eq!(bodies(&conn, "m", "r", "history"), vec![json!({ "content": "one" }), json!({ "content": "two" })]);
+		// a key left behind by a failed removal is reported again but not re-imported
+		let (count, keys) = import_entries(&mut conn, &entries).unwrap();
+		assert_eq!((count, keys.len()), (0, 1));
+		assert_eq!(bodies(&conn, "m", "r", "history").len(), 2);
+		// rooms that only show up later are picked up by a later launch
+		entries.insert("bot-content-m-s".to_string(), json!([{ "content": "three" }]));
+		entries.insert("bot-rooms-m".to_string(), json!([{ "id": "r" }, { "id": "s" }]));
+		let (count, keys) = import_entries(&mut conn, &entries).unwrap();
+		assert_eq!((count, keys.len()), (1, 2));
+	}
+
+	#[test]
+	fn retention_keeps_newest_and_room_overrides_mesh() {
+		let conn = open();
+		let conversation = conversation_id(&conn, "m", "r", "history").unwrap();
+		let other = conversation_id(&conn, "m", "q", "history").unwrap();
+		for i in 0..5 {
+			insert_message(&conn, conversation, now(), &json!({ "content": i })).unwrap();
+			insert_message(&conn, other, now(), &json!({ "content": i })).unwrap();
+		}
+		insert_message(&conn, conversation, now() - 10 * 86400, &json!({ "content": "old" })).unwrap();
+		conn.execute("INSERT INTO retention_policies (mesh, room, max_messages) VALUES ('m', '', 2)", []).unwrap();
+		conn.execute("INSERT INTO retention_policies (mesh, room, max_age_days) VALUES ('m', 'r', 5)", []).unwrap();
+		assert_eq!(apply_all_retention(&conn).unwrap(), 4);
+		// room `r` only drops by age, room `q` falls back to the mesh policy
+		assert_eq!(bodies(&conn, "m", "r", "history").len(), 5);
+		assert_eq!(bodies(&conn, "m", "q", "history"), vec![json!({ "content": 3 }), json!({ "content": 4 })]);
+		assert_eq!(apply_all_retention(&conn).unwrap(), 0);
+	}
+}
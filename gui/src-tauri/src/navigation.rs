@@ -0,0 +1,302 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::Emitter;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use log::{info, warn};
+
+use crate::routing::{self, RuleKind};
+use crate::scope;
+use crate::store;
+use crate::tabs;
+
+// Per-mesh policy for navigations that leave the mesh, i.e. that would not go
+// through the mesh proxy. Pages served over the mesh are trusted by the mesh, the
+// rest of the network is not: `allowlist` only lets through hosts listed in `allow`,
+// `prompt` asks the user first. `on_navigation` cannot block while the user
+// decides, so a prompted navigation is cancelled, announced as `navigation-request`
+// and replayed by `respond_navigation` once approved.
+pub const NAVIGATION_REQUEST_EVENT: &str = "navigation-request";
+// never loaded in a browser webview, whatever the policy
+const BLOCKED_SCHEMES: &[&str] = &["file", "javascript"];
+// an approval not used within this time lapses
+const APPROVAL_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NavigationMode {
+	#[default]
+	Open,
+	Allowlist,
+	Prompt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowEntry {
+	pub kind: RuleKind,
+	pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationPolicy {
+	#[serde(default)]
+	pub mode: NavigationMode,
+	#[serde(default)]
+	pub allow: Vec<AllowEntry>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NavigationRequest {
+	pub id: u64,
+	// webview that tried to navigate
+	pub label: String,
+	pub mesh: String,
+	pub url: String,
+	pub host: String,
+}
+
+lazy_static! {
+	static ref PENDING: Mutex<HashMap<u64, NavigationRequest>> = Mutex::new(HashMap::new());
+	// label -> URLs the webview may load once without asking, with when they were approved
+	static ref APPROVED: Mutex<HashMap<String, Vec<(String, Instant)>>> = Mutex::new(HashMap::new());
+}
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+fn policy_key(mesh: &str) -> String {
+	format!("navigation-policy-{}", mesh)
+}
+
+impl NavigationPolicy {
+	pub fn allows(&self, url: &Url) -> bool {
+		let Some(host) = url.host() else {
+			return false;
+		};
+		self.allow.iter().any(|entry| routing::host_matches(entry.kind, &entry.pattern, &host))
+	}
+}
+
+pub(crate) fn load_policy(app: &AppHandle, mesh: &str) -> NavigationPolicy {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(policy_key(mesh)))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default()
+}
+
+fn save_policy(app: &AppHandle, mesh: &str, policy: &NavigationPolicy) -> Result<(), String> {
+	let key = policy_key(mesh);
+	let store = store::open_store(app)?;
+	store.set(&key, serde_json::to_value(policy).map_err(|e| e.to_string())?);
+	store::notify_store_change(app, &key, "set");
+	Ok(())
+}
+
+pub(crate) fn blocked_scheme(url: &Url) -> bool {
+	BLOCKED_SCHEMES.contains(&url.scheme())
+}
+
+// What the policy of a mesh does with a navigation leaving it.
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+	Allow,
+	Block,
+	Ask,
+}
+
+impl NavigationPolicy {
+	fn verdict(&self, url: &Url) -> Verdict {
+		match self.mode {
+			NavigationMode::Open => Verdict::Allow,
+			_ if self.allows(url) => Verdict::Allow,
+			NavigationMode::Allowlist => Verdict::Block,
+			NavigationMode::Prompt => Verdict::Ask,
+		}
+	}
+}
+
+// Lets `label` load `url` once within APPROVAL_TTL without going through the policy:
+// the initial URL of a tab, an address typed in the toolbar or an approved prompt.
+pub(crate) fn approve(label: &str, url: &str) {
+	let Ok(url) = Url::parse(url) else {
+		return;
+	};
+	APPROVED
+		.lock()
+		.unwrap()
+		.entry(label.to_string())
+		.or_default()
+		.push((url.to_string(), Instant::now()));
+}
+
+fn take_approval(label: &str, url: &Url) -> bool {
+	let mut approved = APPROVED.lock().unwrap();
+	let Some(urls) = approved.get_mut(label) else {
+		return false;
+	};
+	urls.retain(|(_, at)| at.elapsed() < APPROVAL_TTL);
+	let index = urls.iter().position(|(u, _)| u == url.as_str());
+	if let Some(index) = index {
+		urls.remove(index);
+	}
+	if urls.is_empty() {
+		approved.remove(label);
+	}
+	index.is_some()
+}
+
+// Drops the approvals and unanswered prompts of webview `label` once it is gone;
+// labels are reused.
+pub(crate) fn forget(label: &str) {
+	APPROVED.lock().unwrap().remove(label);
+	PENDING.lock().unwrap().retain(|_, request| request.label != label);
+}
+
+// Called from `on_navigation` for navigations leaving the mesh of `label`.
+pub(crate) fn permit(app: &AppHandle, label: &str, mesh: &str, url: &Url) -> bool {
+	if take_approval(label, url) {
+		return true;
+	}
+	match load_policy(app, mesh).verdict(url) {
+		Verdict::Allow => return true,
+		Verdict::Block => {
+			info!("navigation of {} to {} blocked by the policy of {}", label, tabs::loggable(label, url.as_str()), mesh);
+			return false;
+		}
+		Verdict::Ask => {}
+	}
+	let request = NavigationRequest {
+		id: NEXT_REQUEST.fetch_add(1, Ordering::Relaxed),
+		label: label.to_string(),
+		mesh: mesh.to_string(),
+		url: url.to_string(),
+		host: url.host_str().unwrap_or_default().trim_matches(|c| c == '[' || c == ']').to_string(),
+	};
+	PENDING.lock().unwrap().insert(request.id, request.clone());
+	if let Err(e) = app.emit_to(scope::MAIN_WINDOW, NAVIGATION_REQUEST_EVENT, request) {
+		warn!("failed to emit {}: {}", NAVIGATION_REQUEST_EVENT, e);
+	}
+	false
+}
+
+#[command]
+pub async fn get_navigation_policy(
+	app: AppHandle,
+	mesh: String,
+) -> Result<NavigationPolicy, String> {
+	Ok(load_policy(&app, &mesh))
+}
+
+#[command]
+pub async fn set_navigation_policy(
+	app: AppHandle,
+	mesh: String,
+	policy: NavigationPolicy,
+) -> Result<(), String> {
+	for entry in &policy.allow {
+		routing::check_pattern(entry.kind, &entry.pattern)?;
+	}
+	save_policy(&app, &mesh, &policy)
+}
+
+// Prompts that have not been answered yet, e.g. for a UI that was reloaded.
+#[command]
+pub async fn list_navigation_requests() -> Result<Vec<NavigationRequest>, String> {
+	let mut requests: Vec<NavigationRequest> = PENDING.lock().unwrap().values().cloned().collect();
+	requests.sort_by_key(|r| r.id);
+	Ok(requests)
+}
+
+// Answers a `navigation-request`. An approved navigation is replayed in the webview
+// that asked; `remember` also adds its host to the allowlist of the mesh.
+#[command]
+pub async fn respond_navigation(
+	app: AppHandle,
+	id: u64,
+	allow: bool,
+	remember: Option<bool>,
+) -> Result<(), String> {
+	let request = PENDING
+		.lock()
+		.unwrap()
+		.remove(&id)
+		.ok_or_else(|| format!("No pending navigation {}", id))?;
+	if !allow {
+		return Ok(());
+	}
+	if remember.unwrap_or(false) && !request.host.is_empty() {
+		let mut policy = load_policy(&app, &request.mesh);
+		policy.allow.push(AllowEntry {
+			kind: RuleKind::Glob,
+			pattern: request.host.clone(),
+		});
+		save_policy(&app, &request.mesh, &policy)?;
+	}
+	let webview = app
+		.get_webview(&request.label)
+		.ok_or_else(|| format!("Webview {} is gone", request.label))?;
+	let url = Url::parse(&request.url).map_err(|e| e.to_string())?;
+	approve(&request.label, &request.url);
+	webview.navigate(url).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn policy(mode: NavigationMode, allow: &[(RuleKind, &str)]) -> NavigationPolicy {
+		NavigationPolicy {
+			mode,
+			allow: allow.iter().map(|(kind, pattern)| AllowEntry { kind: *kind, pattern: pattern.to_string() }).collect(),
+		}
+	}
+
+	fn url(text: &str) -> Url {
+		Url::parse(text).unwrap()
+	}
+
+	#[test]
+	fn verdict_follows_the_mode() {
+		let allow = [(RuleKind::Suffix, "example.com"), (RuleKind::Cidr, "10.0.0.0/8")];
+		let open = policy(NavigationMode::Open, &[]);
+		assert_eq!(open.verdict(&url("https://anything.test/")), Verdict::Allow);
+		let allowlist = policy(NavigationMode::Allowlist, &allow);
+		assert_eq!(allowlist.verdict(&url("https://docs.example.com/a")), Verdict::Allow);
+		assert_eq!(allowlist.verdict(&url("http://10.1.2.3:8080/")), Verdict::Allow);
+		assert_eq!(allowlist.verdict(&url("https://example.org/")), Verdict::Block);
+		assert_eq!(allowlist.verdict(&url("data:text/plain,x")), Verdict::Block);
+		let prompt = policy(NavigationMode::Prompt, &allow);
+		assert_eq!(prompt.verdict(&url("https://example.com/")), Verdict::Allow);
+		assert_eq!(prompt.verdict(&url("https://example.org/")), Verdict::Ask);
+	}
+
+	#[test]
+	fn approvals_are_used_once() {
+		let page = url("https://example.org/page");
+		approve("t_tab_901", page.as_str());
+		assert!(!take_approval("t_tab_902", &page));
+		assert!(take_approval("t_tab_901", &page));
+		assert!(!take_approval("t_tab_901", &page));
+	}
+
+	#[test]
+	fn approvals_lapse_and_are_forgotten() {
+		let page = url("https://example.org/");
+		APPROVED
+			.lock()
+			.unwrap()
+			.insert("t_tab_903".to_string(), vec![(page.to_string(), Instant::now() - APPROVAL_TTL)]);
+		assert!(!take_approval("t_tab_903", &page));
+		assert!(!APPROVED.lock().unwrap().contains_key("t_tab_903"));
+
+		approve("t_tab_904", page.as_str());
+		forget("t_tab_904");
+		assert!(!take_approval("t_tab_904", &page));
+	}
+}
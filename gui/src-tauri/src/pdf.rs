@@ -0,0 +1,399 @@
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::Webview;
+use tauri::command;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use log::info;
+
+use crate::tabs;
+
+// Printing a browser webview to a PDF file without a dialog: WebKitGTK prints
+// through GTK's file printer, WebView2 through `PrintToPdf`. WKWebView's `createPDF`
+// renders the page as one continuous sheet, so on macOS the page size and margins
+// do not apply. Mobile platforms report that they are not supported.
+// time allowed for a page to be printed
+#[cfg_attr(any(target_os = "ios", target_os = "android"), allow(dead_code))]
+const PRINT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+	A3,
+	#[default]
+	A4,
+	A5,
+	Letter,
+	Legal,
+}
+
+// Millimetres.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PdfMargins {
+	pub top: f64,
+	pub right: f64,
+	pub bottom: f64,
+	pub left: f64,
+}
+
+impl Default for PdfMargins {
+	fn default() -> Self {
+		PdfMargins { top: 10., right: 10., bottom: 10., left: 10. }
+	}
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfOptions {
+	#[serde(default)]
+	pub page_size: PageSize,
+	// custom paper in millimetres, both or neither; overrides `page_size`
+	#[serde(default)]
+	pub page_width: Option<f64>,
+	#[serde(default)]
+	pub page_height: Option<f64>,
+	#[serde(default)]
+	pub landscape: bool,
+	#[serde(default)]
+	pub margins: Option<PdfMargins>,
+}
+
+impl PdfOptions {
+	fn validate(&self) -> Result<(), String> {
+		match (self.page_width, self.page_height) {
+			(None, None) => {}
+			(Some(width), Some(height)) if width > 0. && height > 0. => {}
+			(Some(_), Some(_)) => return Err("Page size must be positive".to_string()),
+			_ => return Err("A custom page size needs both a width and a height".to_string()),
+		}
+		let Some(m) = self.margins else {
+			return Ok(());
+		};
+		if [m.top, m.right, m.bottom, m.left].iter().any(|v| !v.is_finite() || *v < 0.) {
+			return Err("Margins cannot be negative".to_string());
+		}
+		let (width, height) = self.paper();
+		let (width, height) = if self.landscape { (height, width) } else { (width, height) };
+		if m.left + m.right >= width || m.top + m.bottom >= height {
+			return Err("Margins leave no room on the page".to_string());
+		}
+		Ok(())
+	}
+
+	// Portrait width and height of the paper in millimetres.
+	fn paper(&self) -> (f64, f64) {
+		if let (Some(width), Some(height)) = (self.page_width, self.page_height) {
+			return (width, height);
+		}
+		match self.page_size {
+			PageSize::A3 => (297., 420.),
+			PageSize::A4 => (210., 297.),
+			PageSize::A5 => (148., 210.),
+			PageSize::Letter => (215.9, 279.4),
+			PageSize::Legal => (215.9, 355.6),
+		}
+	}
+}
+
+// `label`, or the active tab of `window`.
+fn target_webview(app: &AppHandle, label: Option<String>, window: Option<String>) -> Result<Webview, String> {
+	let label = match (label, window) {
+		(Some(label), _) => label,
+		(None, Some(window)) => tabs::all_windows()
+			.into_iter()
+			.find(|w| w.window == window)
+			.and_then(|w| w.active)
+			.ok_or_else(|| format!("Window {} has no active tab", window))?,
+		(None, None) => return Err("No webview or window given".to_string()),
+	};
+	app.get_webview(&label).ok_or_else(|| format!("No webview {}", label))
+}
+
+fn output_path(path: &str, overwrite: bool) -> Result<PathBuf, String> {
+	let mut path = PathBuf::from(path);
+	if !path.is_absolute() {
+		return Err(format!("{} is not an absolute path", path.display()));
+	}
+	if path.extension().is_none() {
+		path.set_extension("pdf");
+	}
+	if path.exists() && !overwrite {
+		return Err(format!("{} already exists", path.display()));
+	}
+	match path.parent() {
+		Some(dir) if dir.is_dir() => Ok(path),
+		_ => Err(format!("The folder of {} does not exist", path.display())),
+	}
+}
+
+// Name of GTK's file printer, the virtual printer that writes PDF to
+// `output-uri`. Its name is translated, so it is looked up by what it can do.
+#[cfg(target_os = "linux")]
+fn file_printer() -> Option<String> {
+	use gtk::glib::ffi::{gboolean, GFALSE, GTRUE};
+	use std::ffi::{c_char, c_void, CStr};
+
+	unsafe extern "C" {
+		fn gtk_enumerate_printers(
+			func: unsafe extern "C" fn(*mut c_void, *mut c_void) -> gboolean,
+			data: *mut c_void,
+			destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+			wait: gboolean,
+		);
+		fn gtk_printer_is_virtual(printer: *mut c_void) -> gboolean;
+		fn gtk_printer_accepts_pdf(printer: *mut c_void) -> gboolean;
+		fn gtk_printer_get_name(printer: *mut c_void) -> *const c_char;
+	}
+
+	unsafe extern "C" fn visit(printer: *mut c_void, data: *mut c_void) -> gboolean {
+		unsafe {
+			if gtk_printer_is_virtual(printer) == GFALSE || gtk_printer_accepts_pdf(printer) == GFALSE {
+				return GFALSE;
+			}
+			let name = CStr::from_ptr(gtk_printer_get_name(printer)).to_string_lossy().to_string();
+			*(data as *mut Option<String>) = Some(name);
+			GTRUE
+		}
+	}
+
+	let mut found: Option<String> = None;
+	// `wait` returns once every print backend has listed its printers
+	unsafe { gtk_enumerate_printers(visit, &mut found as *mut Option<String> as *mut c_void, None, GTRUE) };
+	found
+}
+
+#[cfg(target_os = "linux")]
+async fn print(webview: &Webview, path: &Path, options: &PdfOptions) -> Result<(), String> {
+	use std::cell::RefCell;
+	use std::rc::Rc;
+	use std::sync::mpsc;
+	use webkit2gtk::PrintOperationExt;
+
+	let uri = url::Url::from_file_path(path).map_err(|_| format!("Invalid path {}", path.display()))?.to_string();
+	let options = options.clone();
+	let (done, result) = mpsc::channel::<Result<(), String>>();
+	webview
+		.with_webview(move |platform| {
+			let Some(printer) = file_printer() else {
+				let _ = done.send(Err("GTK has no printer that writes PDF files".to_string()));
+				return;
+			};
+			let settings = gtk::PrintSettings::new();
+			settings.set_printer(&printer);
+			settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT.as_str(), Some("pdf"));
+			settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI.as_str(), Some(&uri));
+			let setup = gtk::PageSetup::new();
+			let paper = match (options.page_width, options.page_height) {
+				(Some(width), Some(height)) => gtk::PaperSize::new_custom("custom", "Custom", width, height, gtk::Unit::Mm),
+				_ => gtk::PaperSize::new(Some(match options.page_size {
+					PageSize::A3 => "iso_a3",
+					PageSize::A4 => "iso_a4",
+					PageSize::A5 => "iso_a5",
+					PageSize::Letter => "na_letter",
+					PageSize::Legal => "na_legal",
+				})),
+			};
+			setup.set_paper_size(&paper);
+			let orientation = if options.landscape { gtk::PageOrientation::Landscape } else { gtk::PageOrientation::Portrait };
+			setup.set_orientation(orientation);
+			settings.set_orientation(orientation);
+			let margins = options.margins.unwrap_or_default();
+			setup.set_top_margin(margins.top, gtk::Unit::Mm);
+			setup.set_right_margin(margins.right, gtk::Unit::Mm);
+			setup.set_bottom_margin(margins.bottom, gtk::Unit::Mm);
+			setup.set_left_margin(margins.left, gtk::Unit::Mm);
+
+			let operation = webkit2gtk::PrintOperation::new(&platform.inner());
+			operation.set_print_settings(&settings);
+			operation.set_page_setup(&setup);
+			// held until the operation finishes, which always comes after a failure
+			let pending = Rc::new(RefCell::new(Some(operation.clone())));
+			let failed = done.clone();
+			operation.connect_failed(move |_, e| {
+				let _ = failed.send(Err(e.to_string()));
+			});
+			operation.connect_finished(move |_| {
+				pending.borrow_mut().take();
+				let _ = done.send(Ok(()));
+			});
+			operation.print();
+		})
+		.map_err(|e| e.to_string())?;
+	tauri::async_runtime::spawn_blocking(move || result.recv_timeout(PRINT_TIMEOUT))
+		.await
+		.map_err(|e| e.to_string())?
+		.map_err(|_| "Printing did not finish in time".to_string())?
+}
+
+// Starts WebView2's `PrintToPdf`; `done` gets the outcome once the file is written.
+#[cfg(windows)]
+fn start_print(
+	controller: webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2Controller,
+	file: &windows::core::HSTRING,
+	options: &PdfOptions,
+	done: std::sync::mpsc::Sender<Result<(), String>>,
+) -> windows::core::Result<()> {
+	use webview2_com::Microsoft::Web::WebView2::Win32::{
+		ICoreWebView2Environment6, ICoreWebView2_2, ICoreWebView2_7, COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE,
+		COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT,
+	};
+	use webview2_com::PrintToPdfCompletedHandler;
+	use windows::core::Interface;
+
+	// WebView2 measures in inches
+	const MM_PER_INCH: f64 = 25.4;
+	let (width, height) = options.paper();
+	let margins = options.margins.unwrap_or_default();
+	let orientation = if options.landscape { COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE } else { COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT };
+	unsafe {
+		let core = controller.CoreWebView2()?;
+		let settings = core
+			.cast::<ICoreWebView2_2>()?
+			.Environment()?
+			.cast::<ICoreWebView2Environment6>()?
+			.CreatePrintSettings()?;
+		settings.SetOrientation(orientation)?;
+		settings.SetPageWidth(width / MM_PER_INCH)?;
+		settings.SetPageHeight(height / MM_PER_INCH)?;
+		settings.SetMarginTop(margins.top / MM_PER_INCH)?;
+		settings.SetMarginRight(margins.right / MM_PER_INCH)?;
+		settings.SetMarginBottom(margins.bottom / MM_PER_INCH)?;
+		settings.SetMarginLeft(margins.left / MM_PER_INCH)?;
+		settings.SetShouldPrintBackgrounds(true)?;
+		let handler = PrintToPdfCompletedHandler::create(Box::new(move |status, written| {
+			let _ = done.send(match status {
+				Err(e) => Err(e.to_string()),
+				Ok(()) if !written => Err("WebView2 could not write the PDF".to_string()),
+				Ok(()) => Ok(()),
+			});
+			Ok(())
+		}));
+		core.cast::<ICoreWebView2_7>()?.PrintToPdf(file, &settings, &handler)
+	}
+}
+
+#[cfg(windows)]
+async fn print(webview: &Webview, path: &Path, options: &PdfOptions) -> Result<(), String> {
+	use std::sync::mpsc;
+
+	let file = windows::core::HSTRING::from(path.as_os_str());
+	let options = options.clone();
+	let (done, result) = mpsc::channel::<Result<(), String>>();
+	webview
+		.with_webview(move |platform| {
+			if let Err(e) = start_print(platform.controller(), &file, &options, done.clone()) {
+				let _ = done.send(Err(e.to_string()));
+			}
+		})
+		.map_err(|e| e.to_string())?;
+	tauri::async_runtime::spawn_blocking(move || result.recv_timeout(PRINT_TIMEOUT))
+		.await
+		.map_err(|e| e.to_string())?
+		.map_err(|_| "Printing did not finish in time".to_string())?
+}
+
+#[cfg(target_os = "macos")]
+async fn print(webview: &Webview, path: &Path, _options: &PdfOptions) -> Result<(), String> {
+	use std::sync::mpsc;
+	use block2::RcBlock;
+	use objc2_foundation::{NSData, NSError};
+	use objc2_web_kit::WKWebView;
+
+	let (done, result) = mpsc::channel::<Result<Vec<u8>, String>>();
+	webview
+		.with_webview(move |platform| {
+			let handler = RcBlock::new(move |data: *mut NSData, error: *mut NSError| {
+				// exactly one of them is set
+				let outcome = match unsafe { (data.as_ref(), error.as_ref()) } {
+					(Some(data), _) => Ok(data.to_vec()),
+					(None, Some(error)) => Err(error.localizedDescription().to_string()),
+					(None, None) => Err("WebKit returned no PDF".to_string()),
+				};
+				let _ = done.send(outcome);
+			});
+			unsafe {
+				let view = &*(platform.inner() as *const WKWebView);
+				view.createPDFWithConfiguration_completionHandler(None, &handler);
+			}
+		})
+		.map_err(|e| e.to_string())?;
+	let pdf = tauri::async_runtime::spawn_blocking(move || result.recv_timeout(PRINT_TIMEOUT))
+		.await
+		.map_err(|e| e.to_string())?
+		.map_err(|_| "Printing did not finish in time".to_string())??;
+	std::fs::write(path, pdf).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(any(target_os = "ios", target_os = "android"))]
+async fn print(_webview: &Webview, _path: &Path, _options: &PdfOptions) -> Result<(), String> {
+	Err("Printing to PDF is not supported on this platform".to_string())
+}
+
+// Prints webview `label`, or the active tab of `window`, to `path`, which must not
+// exist unless `overwrite` is set. Returns the path written, with `.pdf` added when
+// it has no extension.
+#[command]
+pub async fn print_to_pdf(
+	app: AppHandle,
+	label: Option<String>,
+	window: Option<String>,
+	path: String,
+	options: Option<PdfOptions>,
+	overwrite: Option<bool>,
+) -> Result<String, String> {
+	let webview = target_webview(&app, label, window)?;
+	let path = output_path(&path, overwrite.unwrap_or(false))?;
+	let options = options.unwrap_or_default();
+	options.validate()?;
+	print(&webview, &path, &options).await?;
+	info!("printed {} to {}", webview.label(), path.display());
+	Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn options(width: Option<f64>, height: Option<f64>, margins: Option<PdfMargins>) -> PdfOptions {
+		PdfOptions { page_width: width, page_height: height, margins, ..Default::default() }
+	}
+
+	fn margins(top: f64, right: f64, bottom: f64, left: f64) -> Option<PdfMargins> {
+		Some(PdfMargins { top, right, bottom, left })
+	}
+
+	#[test]
+	fn custom_size_needs_both_sides() {
+		assert!(options(None, None, None).validate().is_ok());
+		assert!(options(Some(100.), Some(150.), None).validate().is_ok());
+		assert!(options(Some(100.), None, None).validate().is_err());
+		assert!(options(None, Some(150.), None).validate().is_err());
+		assert!(options(Some(0.), Some(150.), None).validate().is_err());
+	}
+
+	#[test]
+	fn margins_are_checked() {
+		assert!(options(None, None, margins(0., 5., 10., 20.)).validate().is_ok());
+		assert!(options(None, None, margins(-1., 5., 10., 20.)).validate().is_err());
+		assert!(options(None, None, margins(f64::NAN, 5., 10., 20.)).validate().is_err());
+		assert!(options(Some(100.), Some(150.), margins(10., 50., 10., 50.)).validate().is_err());
+		let mut landscape = options(Some(100.), Some(150.), margins(10., 50., 10., 50.));
+		landscape.landscape = true;
+		assert!(landscape.validate().is_ok());
+	}
+
+	#[test]
+	fn existing_files_are_kept() {
+		let dir = std::env::temp_dir().join(format!("ztm-pdf-test-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = dir.join("page.pdf");
+		std::fs::write(&file, b"%PDF").unwrap();
+		let path = file.to_string_lossy().to_string();
+		assert!(output_path(&path, false).is_err());
+		assert_eq!(output_path(&path, true), Ok(file.clone()));
+		assert_eq!(output_path(&dir.join("other").to_string_lossy(), false), Ok(dir.join("other.pdf")));
+		assert!(output_path("relative.pdf", false).is_err());
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
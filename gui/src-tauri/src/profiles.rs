@@ -0,0 +1,182 @@
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use log::warn;
+
+use crate::tabs;
+
+// Every mesh/proxy combination browses with its own cookies, local storage and
+// cache, kept under `<AppData>/profiles/<id>`. The id is stable for a combination,
+// so a profile survives restarts; plain webviews (no mesh, no proxy) keep using the
+// default data directory shared with the main window.
+const PROFILES_DIR: &str = "profiles";
+const PROFILE_META: &str = "profile.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ProfileMeta {
+	mesh: String,
+	proxy: String,
+	created_at: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Profile {
+	pub id: String,
+	pub mesh: String,
+	pub proxy: String,
+	pub created_at: u64,
+	pub size: u64,
+	// a browser tab is currently using it
+	pub in_use: bool,
+}
+
+fn digest(mesh: &str, proxy: &str) -> [u8; 32] {
+	Sha256::digest(format!("{}\n{}", mesh, proxy).as_bytes()).into()
+}
+
+// `{mesh}-{hash}`, with the mesh name reduced to characters safe in a path.
+pub(crate) fn profile_id(mesh: &str, proxy: &str) -> Option<String> {
+	if mesh.is_empty() && proxy.is_empty() {
+		return None;
+	}
+	let slug: String = mesh
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+		.collect();
+	let hash: String = digest(mesh, proxy)[..8].iter().map(|b| format!("{:02x}", b)).collect();
+	Some(format!("{}-{}", if slug.is_empty() { "direct" } else { &slug }, hash))
+}
+
+// WKWebView ignores data directories and keys persistent stores by a 16 byte id instead.
+#[cfg(target_vendor = "apple")]
+fn store_identifier(mesh: &str, proxy: &str) -> [u8; 16] {
+	let mut id = [0u8; 16];
+	id.copy_from_slice(&digest(mesh, proxy)[..16]);
+	id
+}
+
+fn profiles_dir(app: &AppHandle) -> Result<PathBuf, String> {
+	Ok(app.path().app_data_dir().map_err(|e| e.to_string())?.join(PROFILES_DIR))
+}
+
+fn prepare_profile(app: &AppHandle, id: &str, mesh: &str, proxy: &str) -> Result<PathBuf, String> {
+	let dir = profiles_dir(app)?.join(id);
+	fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	let meta_path = dir.join(PROFILE_META);
+	if !meta_path.exists() {
+		let meta = ProfileMeta {
+			mesh: mesh.to_string(),
+			proxy: proxy.to_string(),
+			created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+		};
+		fs::write(&meta_path, serde_json::to_vec(&meta).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+	}
+	Ok(dir)
+}
+
+// Points `builder` at the profile of `mesh`/`proxy`. If the profile cannot be
+// created the webview still opens, but incognito so nothing leaks into the default store.
+pub(crate) fn apply_profile(
+	app: &AppHandle,
+	builder: tauri::WebviewBuilder<tauri::Wry>,
+	mesh: &str,
+	proxy: &str,
+) -> tauri::WebviewBuilder<tauri::Wry> {
+	let Some(id) = profile_id(mesh, proxy) else {
+		return builder;
+	};
+	match prepare_profile(app, &id, mesh, proxy) {
+		Ok(dir) => {
+			#[cfg(target_vendor = "apple")]
+			let builder = builder.data_store_identifier(store_identifier(mesh, proxy));
+			builder.data_directory(dir)
+		}
+		Err(e) => {
+			warn!("browser profile {} unavailable, browsing incognito: {}", id, e);
+			builder.incognito(true)
+		}
+	}
+}
+
+fn dir_size(path: &Path) -> u64 {
+	let Ok(entries) = fs::read_dir(path) else {
+		return 0;
+	};
+	entries
+		.flatten()
+		.map(|entry| match entry.file_type() {
+			Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+			Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+			Err(_) => 0,
+		})
+		.sum()
+}
+
+fn profile_in_use(id: &str) -> bool {
+	tabs::all_tabs()
+		.iter()
+		.any(|t| profile_id(&t.mesh, &t.proxy).as_deref() == Some(id))
+}
+
+#[command]
+pub async fn list_browser_profiles(app: AppHandle) -> Result<Vec<Profile>, String> {
+	let dir = profiles_dir(&app)?;
+	if !dir.exists() {
+		return Ok(vec![]);
+	}
+	let mut profiles = vec![];
+	for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+		let id = entry.file_name().to_string_lossy().to_string();
+		let meta = fs::read(entry.path().join(PROFILE_META))
+			.ok()
+			.and_then(|bytes| serde_json::from_slice::<ProfileMeta>(&bytes).ok());
+		let Some(meta) = meta else {
+			continue;
+		};
+		profiles.push(Profile {
+			in_use: profile_in_use(&id),
+			size: dir_size(&entry.path()),
+			id,
+			mesh: meta.mesh,
+			proxy: meta.proxy,
+			created_at: meta.created_at,
+		});
+	}
+	profiles.sort_by(|a, b| a.mesh.cmp(&b.mesh).then(a.proxy.cmp(&b.proxy)));
+	Ok(profiles)
+}
+
+// Deletes the stored browsing data of one profile, or of every profile of `mesh`.
+// Profiles still used by an open tab are refused; close their tabs first.
+#[command]
+pub async fn clear_browser_profiles(
+	app: AppHandle,
+	id: Option<String>,
+	mesh: Option<String>,
+) -> Result<Vec<String>, String> {
+	let targets: Vec<Profile> = list_browser_profiles(app.clone())
+		.await?
+		.into_iter()
+		.filter(|p| id.as_ref().is_none_or(|id| &p.id == id))
+		.filter(|p| mesh.as_ref().is_none_or(|mesh| &p.mesh == mesh))
+		.collect();
+	if let Some(busy) = targets.iter().find(|p| p.in_use) {
+		return Err(format!("Browser profile {} is in use, close its tabs first", busy.id));
+	}
+	let dir = profiles_dir(&app)?;
+	let mut cleared = vec![];
+	for profile in targets {
+		#[cfg(target_vendor = "apple")]
+		if let Err(e) = app.remove_data_store(store_identifier(&profile.mesh, &profile.proxy)).await {
+			warn!("failed to remove data store of {}: {}", profile.id, e);
+		}
+		fs::remove_dir_all(dir.join(&profile.id)).map_err(|e| format!("Failed to clear {}: {}", profile.id, e))?;
+		cleared.push(profile.id);
+	}
+	Ok(cleared)
+}
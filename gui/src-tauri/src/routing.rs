@@ -0,0 +1,297 @@
+use tauri::AppHandle;
+use tauri::command;
+use std::net::IpAddr;
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+
+use crate::store;
+
+// Per-mesh rules deciding whether a browser navigation goes through the mesh proxy,
+// straight to the network, or nowhere. Rules are evaluated in order and the first
+// match wins; everything else falls back to `default_action`, which keeps the route
+// of the tab unless set otherwise. Matching is pure, so
+// `route` can be exercised without a webview (see `test_proxy_route`).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteAction {
+	// stay on the route of the tab, proxied or direct
+	#[default]
+	Keep,
+	Proxy,
+	Direct,
+	Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+	// `*.corp.local`, `cdn-?.example.com`; `*` matches any run of characters, `?` exactly one
+	Glob,
+	// `10.0.0.0/8`, `fd00::/8`; only matches hosts that are IP literals
+	Cidr,
+	// `example.com` matches the domain itself and every subdomain
+	Suffix,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteRule {
+	pub kind: RuleKind,
+	pub pattern: String,
+	pub action: RouteAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteRules {
+	#[serde(default)]
+	pub default_action: RouteAction,
+	#[serde(default)]
+	pub rules: Vec<RouteRule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteDecision {
+	pub action: RouteAction,
+	// index into `rules` of the rule that decided, `None` for the default action
+	pub rule: Option<usize>,
+}
+
+fn rules_key(mesh: &str) -> String {
+	format!("proxy-rules-{}", mesh)
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+	let (mut p, mut t) = (0, 0);
+	let (mut star, mut mark) = (None, 0);
+	while t < text.len() {
+		if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+			p += 1;
+			t += 1;
+		} else if p < pattern.len() && pattern[p] == b'*' {
+			star = Some(p);
+			mark = t;
+			p += 1;
+		} else if let Some(s) = star {
+			p = s + 1;
+			mark += 1;
+			t = mark;
+		} else {
+			return false;
+		}
+	}
+	pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn cidr_match(pattern: &str, ip: IpAddr) -> bool {
+	let (network, bits) = match pattern.split_once('/') {
+		Some((network, bits)) => (network, bits.parse::<u32>().ok()),
+		None => (pattern, None),
+	};
+	let Ok(network) = network.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() else {
+		return false;
+	};
+	match (network, ip) {
+		(IpAddr::V4(network), IpAddr::V4(ip)) => {
+			let bits = bits.unwrap_or(32).min(32);
+			let mask = if bits == 0 { 0 } else { u32::MAX << (32 - bits) };
+			u32::from(network) & mask == u32::from(ip) & mask
+		}
+		(IpAddr::V6(network), IpAddr::V6(ip)) => {
+			let bits = bits.unwrap_or(128).min(128);
+			let mask = if bits == 0 { 0 } else { u128::MAX << (128 - bits) };
+			u128::from(network) & mask == u128::from(ip) & mask
+		}
+		_ => false,
+	}
+}
+
+// Shared with the navigation allowlists, which use the same pattern kinds.
+pub(crate) fn host_matches(kind: RuleKind, pattern: &str, host: &Host<&str>) -> bool {
+	let pattern = pattern.trim().to_ascii_lowercase();
+	match (kind, host) {
+		(RuleKind::Cidr, Host::Ipv4(ip)) => cidr_match(&pattern, IpAddr::V4(*ip)),
+		(RuleKind::Cidr, Host::Ipv6(ip)) => cidr_match(&pattern, IpAddr::V6(*ip)),
+		(RuleKind::Cidr, Host::Domain(_)) => false,
+		(RuleKind::Glob, host) => glob_match(pattern.as_bytes(), host_text(host).as_bytes()),
+		(RuleKind::Suffix, host) => {
+			let host = host_text(host);
+			let suffix = pattern.trim_start_matches('.');
+			host == suffix || host.ends_with(&format!(".{}", suffix))
+		}
+	}
+}
+
+pub(crate) fn check_pattern(kind: RuleKind, pattern: &str) -> Result<(), String> {
+	if kind != RuleKind::Cidr {
+		return Ok(());
+	}
+	let invalid = || format!("Invalid CIDR rule: {}", pattern);
+	let (network, bits) = match pattern.trim().split_once('/') {
+		Some((network, bits)) => (network, Some(bits.parse::<u32>().map_err(|_| invalid())?)),
+		None => (pattern.trim(), None),
+	};
+	let max = match network.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() {
+		Ok(IpAddr::V4(_)) => 32,
+		Ok(IpAddr::V6(_)) => 128,
+		Err(_) => return Err(invalid()),
+	};
+	if bits.is_some_and(|bits| bits > max) {
+		return Err(invalid());
+	}
+	Ok(())
+}
+
+impl RouteRule {
+	pub fn matches(&self, host: &Host<&str>) -> bool {
+		host_matches(self.kind, &self.pattern, host)
+	}
+}
+
+fn host_text(host: &Host<&str>) -> String {
+	match host {
+		Host::Domain(domain) => domain.trim_end_matches('.').to_ascii_lowercase(),
+		Host::Ipv4(ip) => ip.to_string(),
+		Host::Ipv6(ip) => ip.to_string(),
+	}
+}
+
+// Decides how `url` should be fetched. URLs without a host (about:, data:) keep
+// the route of the tab.
+pub fn route(rules: &RouteRules, url: &Url) -> RouteDecision {
+	let Some(host) = url.host() else {
+		return RouteDecision { action: RouteAction::Keep, rule: None };
+	};
+	match rules.rules.iter().position(|rule| rule.matches(&host)) {
+		Some(index) => RouteDecision { action: rules.rules[index].action, rule: Some(index) },
+		None => RouteDecision { action: rules.default_action, rule: None },
+	}
+}
+
+pub(crate) fn load_rules(app: &AppHandle, mesh: &str) -> RouteRules {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(rules_key(mesh)))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default()
+}
+
+#[command]
+pub async fn get_proxy_rules(
+	app: AppHandle,
+	mesh: String,
+) -> Result<RouteRules, String> {
+	Ok(load_rules(&app, &mesh))
+}
+
+#[command]
+pub async fn set_proxy_rules(
+	app: AppHandle,
+	mesh: String,
+	rules: RouteRules,
+) -> Result<(), String> {
+	for rule in &rules.rules {
+		check_pattern(rule.kind, &rule.pattern)?;
+	}
+	let key = rules_key(&mesh);
+	let store = store::open_store(&app)?;
+	store.set(&key, serde_json::to_value(&rules).map_err(|e| e.to_string())?);
+	store::notify_store_change(&app, &key, "set");
+	Ok(())
+}
+
+// Dry run of the rules of `mesh` (or of the given, unsaved `rules`) against `url`.
+#[command]
+pub async fn test_proxy_route(
+	app: AppHandle,
+	mesh: String,
+	url: String,
+	rules: Option<RouteRules>,
+) -> Result<RouteDecision, String> {
+	let url = Url::parse(&url).map_err(|e| e.to_string())?;
+	let rules = rules.unwrap_or_else(|| load_rules(&app, &mesh));
+	Ok(route(&rules, &url))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matches(kind: RuleKind, pattern: &str, url: &str) -> bool {
+		host_matches(kind, pattern, &Url::parse(url).unwrap().host().unwrap())
+	}
+
+	fn rule(kind: RuleKind, pattern: &str, action: RouteAction) -> RouteRule {
+		RouteRule { kind, pattern: pattern.to_string(), action }
+	}
+
+	#[test]
+	fn glob() {
+		assert!(matches(RuleKind::Glob, "*.corp.local", "http://a.b.corp.local/"));
+		assert!(!matches(RuleKind::Glob, "*.corp.local", "http://corp.local/"));
+		assert!(matches(RuleKind::Glob, "cdn-?.example.com", "https://cdn-1.example.com/"));
+		assert!(!matches(RuleKind::Glob, "cdn-?.example.com", "https://cdn-12.example.com/"));
+		assert!(matches(RuleKind::Glob, "*.EXAMPLE.com", "https://www.example.com./"));
+		assert!(matches(RuleKind::Glob, "10.0.*", "http://10.0.3.4/"));
+		assert!(matches(RuleKind::Glob, "*", "http://anything/"));
+	}
+
+	#[test]
+	fn suffix() {
+		assert!(matches(RuleKind::Suffix, "example.com", "http://example.com/"));
+		assert!(matches(RuleKind::Suffix, ".example.com", "http://a.b.example.com/"));
+		assert!(!matches(RuleKind::Suffix, "example.com", "http://badexample.com/"));
+		assert!(!matches(RuleKind::Suffix, "example.com", "http://example.com.evil/"));
+	}
+
+	#[test]
+	fn cidr() {
+		assert!(matches(RuleKind::Cidr, "10.0.0.0/8", "http://10.20.30.40/"));
+		assert!(!matches(RuleKind::Cidr, "10.0.0.0/8", "http://11.0.0.1/"));
+		assert!(matches(RuleKind::Cidr, "192.168.1.7", "http://192.168.1.7:8080/"));
+		assert!(matches(RuleKind::Cidr, "0.0.0.0/0", "http://8.8.8.8/"));
+		assert!(matches(RuleKind::Cidr, "fd00::/8", "http://[fd12::1]/"));
+		assert!(!matches(RuleKind::Cidr, "fd00::/8", "http://[fe80::1]/"));
+		assert!(!matches(RuleKind::Cidr, "10.0.0.0/8", "http://[::ffff:10.0.0.1]/"));
+		assert!(!matches(RuleKind::Cidr, "10.0.0.0/8", "http://ten.example/"));
+	}
+
+	#[test]
+	fn cidr_patterns_are_checked() {
+		for pattern in ["10.0.0.0/8", "10.0.0.1", "fd00::/8", "[::1]/128", "0.0.0.0/0"] {
+			assert!(check_pattern(RuleKind::Cidr, pattern).is_ok(), "{}", pattern);
+		}
+		for pattern in ["10.0.0.0/33", "fd00::/129", "10.0.0.0/x", "10.0.0.0/", "example.com/8", ""] {
+			assert!(check_pattern(RuleKind::Cidr, pattern).is_err(), "{}", pattern);
+		}
+		assert!(check_pattern(RuleKind::Glob, "anything").is_ok());
+	}
+
+	#[test]
+	fn first_matching_rule_wins() {
+		let rules = RouteRules {
+			default_action: RouteAction::Keep,
+			rules: vec![
+				rule(RuleKind::Suffix, "ads.example.com", RouteAction::Block),
+				rule(RuleKind::Suffix, "example.com", RouteAction::Direct),
+				rule(RuleKind::Cidr, "10.0.0.0/8", RouteAction::Proxy),
+			],
+		};
+		let decide = |url: &str| {
+			let decision = route(&rules, &Url::parse(url).unwrap());
+			(decision.action, decision.rule)
+		};
+		assert_eq!(decide("https://x.ads.example.com/"), (RouteAction::Block, Some(0)));
+		assert_eq!(decide("https://www.example.com/"), (RouteAction::Direct, Some(1)));
+		assert_eq!(decide("http://10.1.2.3/"), (RouteAction::Proxy, Some(2)));
+		assert_eq!(decide("https://other.org/"), (RouteAction::Keep, None));
+		assert_eq!(decide("about:blank"), (RouteAction::Keep, None));
+	}
+
+	#[test]
+	fn rules_default_to_keeping_the_route() {
+		let rules: RouteRules = serde_json::from_str("{}").unwrap();
+		assert_eq!(rules.default_action, RouteAction::Keep);
+		assert_eq!(route(&rules, &Url::parse("https://example.com/").unwrap()).action, RouteAction::Keep);
+	}
+}
@@ -0,0 +1,93 @@
+use tauri::ipc::Invoke;
+use log::warn;
+
+// Pages in browser webviews are arbitrary websites reached over the mesh. Their
+// webviews are labelled `{name}_webview` (`create_proxy_webview`) or
+// `{window}_tab_{n}` (`tabs`), which `capabilities/browser.json` matches to only
+// grant event listening. `capabilities/main.json` covers the app windows, so the
+// plugin permissions stay out of reach; app commands are not covered by
+// capabilities and are filtered here instead, in front of every handler.
+
+// What the toolbar needs: bookmarks of the tab's mesh and navigation. The history is
+// shown through `toolbar_history_menu`, which never returns it to the page.
+const BROWSER_COMMANDS: &[&str] = &[
+	"list_bookmarks",
+	"find_bookmark",
+	"toolbar_bookmark",
+	"toolbar_navigate",
+	"toolbar_back",
+	"toolbar_forward",
+	"toolbar_reload",
+	"toolbar_history_menu",
+	"open_tab",
+	"open_download_folder",
+	"subscribe_store_changes",
+	"unsubscribe_store_changes",
+];
+
+// Label of the app window. Events about every tab (state, loads, navigation prompts)
+// are sent to it only, since browser webviews may listen to events.
+pub(crate) const MAIN_WINDOW: &str = "main";
+
+// Store keys a browser webview may watch for changes.
+pub(crate) const BROWSER_STORE_PREFIXES: &[&str] = &["bookmarks"];
+
+// Windows `capabilities/main.json` grants the plugin permissions to, for every webview
+// they hold. Browser webviews must never be added to one.
+pub(crate) fn is_app_window(label: &str) -> bool {
+	label == MAIN_WINDOW || label.ends_with("-webview")
+}
+
+pub(crate) fn is_browser_webview(label: &str) -> bool {
+	label.ends_with("_webview") || label.contains("_tab_")
+}
+
+pub(crate) fn permits(label: &str, command: &str) -> bool {
+	!is_browser_webview(label) || BROWSER_COMMANDS.contains(&command)
+}
+
+// Wraps the handler generated by `tauri::generate_handler!`.
+pub fn guard<F>(handler: F) -> impl Fn(Invoke) -> bool + Send + Sync + 'static
+where
+	F: Fn(Invoke) -> bool + Send + Sync + 'static,
+{
+	move |invoke| {
+		let label = invoke.message.webview_ref().label().to_string();
+		let command = invoke.message.command().to_string();
+		if permits(&label, &command) {
+			return handler(invoke);
+		}
+		warn!("command {} refused to browser webview {}", command, label);
+		invoke.resolver.reject(format!("Command {} is not available to browser pages", command));
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn browser_webviews_only_get_browser_commands() {
+		for label in ["site_webview", "private_site_webview", "browser_tab_3", "private_site_tab_12"] {
+			assert!(is_browser_webview(label), "{}", label);
+			assert!(permits(label, "toolbar_navigate"));
+			assert!(permits(label, "open_tab"));
+			assert!(!permits(label, "search_history"));
+			assert!(!permits(label, "export_backup"));
+			assert!(!permits(label, "set_navigation_policy"));
+		}
+		for label in ["main", "site-webview"] {
+			assert!(!is_browser_webview(label), "{}", label);
+			assert!(permits(label, "export_backup"));
+		}
+	}
+
+	#[test]
+	fn app_windows_are_recognised() {
+		assert!(is_app_window(MAIN_WINDOW));
+		assert!(is_app_window("site-webview"));
+		assert!(!is_app_window("browser"));
+		assert!(!is_app_window("private_site"));
+	}
+}
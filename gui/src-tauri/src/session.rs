@@ -0,0 +1,314 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::{LogicalPosition, LogicalSize, WindowEvent};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use log::{info, warn};
+
+use crate::agent;
+use crate::proxy::parse_proxy;
+use crate::scope;
+use crate::store;
+use crate::tabs;
+
+// Browser windows and their tabs (private windows excepted), saved in the `browser-session` store key whenever
+// the tab registry changes (debounced) and when the main window is gone. At launch
+// the saved session is kept aside and offered for restore once the agent reports
+// the mesh proxies it needs as listening. Until it is restored or discarded, the
+// windows left of it are saved along with the live ones.
+const SESSION_KEY: &str = "browser-session";
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+const PROBE_INTERVAL: Duration = Duration::from_secs(3);
+// give up waiting for missing proxies and offer what can be restored
+const RESTORE_DEADLINE: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionTab {
+	pub label: String,
+	pub url: String,
+	#[serde(default)]
+	pub title: String,
+	#[serde(default)]
+	pub proxy: String,
+	#[serde(default)]
+	pub mesh: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+	pub label: String,
+	// logical pixels
+	pub width: f64,
+	pub height: f64,
+	pub x: Option<f64>,
+	pub y: Option<f64>,
+	pub active: Option<String>,
+	pub tabs: Vec<SessionTab>,
+}
+
+lazy_static! {
+	// session of the previous run, until it is restored or discarded
+	static ref PREVIOUS: Mutex<Vec<SessionWindow>> = Mutex::new(Vec::new());
+}
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+// set once the saved session has been read, so no save can overwrite it before
+static LOADED: AtomicBool = AtomicBool::new(false);
+// set once the app is closing, so windows being torn down do not empty the session
+static FROZEN: AtomicBool = AtomicBool::new(false);
+
+fn current(app: &AppHandle) -> Vec<SessionWindow> {
+	tabs::all_windows()
+		.into_iter()
+		.filter(|w| !w.tabs.is_empty() && !w.incognito)
+		.map(|w| {
+			let window = app.get_window(&w.window);
+			let scale = window.as_ref().and_then(|win| win.scale_factor().ok()).unwrap_or(1.);
+			let size = window.as_ref().and_then(|win| win.inner_size().ok()).map(|s| s.to_logical::<f64>(scale));
+			let position = window.as_ref().and_then(|win| win.outer_position().ok()).map(|p| p.to_logical::<f64>(scale));
+			SessionWindow {
+				label: w.window,
+				width: size.map(|s| s.width).unwrap_or(1280.),
+				height: size.map(|s| s.height).unwrap_or(860.),
+				x: position.map(|p| p.x),
+				y: position.map(|p| p.y),
+				active: w.active,
+				tabs: w
+					.tabs
+					.into_iter()
+					.map(|t| SessionTab {
+						label: t.label,
+						url: t.url,
+						title: t.title,
+						proxy: t.proxy,
+						mesh: t.mesh,
+					})
+					.collect(),
+			}
+		})
+		.collect()
+}
+
+fn save(app: &AppHandle) {
+	let mut windows = current(app);
+	let open: HashSet<String> = windows.iter().map(|w| w.label.clone()).collect();
+	windows.extend(PREVIOUS.lock().unwrap().iter().filter(|w| !open.contains(&w.label)).cloned());
+	let result = store::open_store(app).and_then(|store| {
+		let value = serde_json::to_value(windows).map_err(|e| e.to_string())?;
+		store.set(SESSION_KEY, value);
+		Ok(())
+	});
+	if let Err(e) = result {
+		warn!("failed to save browser session: {}", e);
+	}
+}
+
+// Schedules a save; called by the tab registry on every change.
+pub(crate) fn touch(app: &AppHandle) {
+	if !LOADED.load(Ordering::Relaxed) || FROZEN.load(Ordering::Relaxed) || SAVE_PENDING.swap(true, Ordering::Relaxed) {
+		return;
+	}
+	let app = app.clone();
+	thread::spawn(move || {
+		thread::sleep(SAVE_DELAY);
+		SAVE_PENDING.store(false, Ordering::Relaxed);
+		if !FROZEN.load(Ordering::Relaxed) {
+			save(&app);
+		}
+	});
+}
+
+fn freeze(app: &AppHandle) {
+	if !FROZEN.swap(true, Ordering::Relaxed) {
+		save(app);
+	}
+}
+
+// Whether a proxy listening on `listen` (`ip:port`) is reached at `host`:`port`.
+fn listens_on(listen: &str, host: &str, port: u16) -> bool {
+	let Some((ip, listen_port)) = listen.rsplit_once(':') else {
+		return false;
+	};
+	let ip = ip.trim_matches(|c| c == '[' || c == ']');
+	let host = host.trim_matches(|c| c == '[' || c == ']');
+	listen_port.parse() == Ok(port) && (ip == host || matches!(ip, "" | "0.0.0.0" | "::"))
+}
+
+// Asks the agent whether the proxy app of `mesh` is up on `proxy`. Proxies outside a
+// mesh are not managed by the agent and count as up.
+fn proxy_ready(app: &AppHandle, mesh: &str, proxy: &str) -> bool {
+	if proxy.is_empty() || mesh.is_empty() {
+		return true;
+	}
+	let Ok(spec) = parse_proxy(proxy) else {
+		return false;
+	};
+	match tauri::async_runtime::block_on(agent::proxy_listen(app, mesh)) {
+		Ok(Some(listen)) => listens_on(&listen, &spec.host, spec.port),
+		Ok(None) => false,
+		Err(e) => {
+			info!("proxy of {} not ready: {}", mesh, e);
+			false
+		}
+	}
+}
+
+// Windows of the previous session whose proxies are all up.
+fn restorable(app: &AppHandle, previous: &[SessionWindow]) -> (Vec<SessionWindow>, bool) {
+	let proxies: HashSet<(&str, &str)> = previous
+		.iter()
+		.flat_map(|w| w.tabs.iter().map(|t| (t.mesh.as_str(), t.proxy.as_str())))
+		.collect();
+	let up: HashSet<(&str, &str)> = proxies.iter().copied().filter(|(mesh, proxy)| proxy_ready(app, mesh, proxy)).collect();
+	let ready: Vec<SessionWindow> = previous
+		.iter()
+		.filter(|w| w.tabs.iter().all(|t| up.contains(&(t.mesh.as_str(), t.proxy.as_str()))))
+		.cloned()
+		.collect();
+	(ready, up.len() == proxies.len())
+}
+
+fn restore(app: &AppHandle, windows: Vec<SessionWindow>) {
+	let restored: HashSet<String> = windows.iter().map(|w| w.label.clone()).collect();
+	PREVIOUS.lock().unwrap().retain(|w| !restored.contains(&w.label));
+	for saved in windows {
+		for tab in &saved.tabs {
+			let background = saved.active.as_ref() != Some(&tab.label);
+			if let Err(e) = tabs::open_tab_in(app, &saved.label, &tab.url, &tab.proxy, &tab.mesh, background, false) {
+				warn!("failed to restore {} in {}: {}", tab.url, saved.label, e);
+			}
+		}
+		let Some(window) = app.get_window(&saved.label) else {
+			continue;
+		};
+		let mut result = window.set_size(LogicalSize::new(saved.width, saved.height));
+		if let (Some(x), Some(y)) = (saved.x, saved.y) {
+			result = result.and_then(|_| window.set_position(LogicalPosition::new(x, y)));
+		}
+		if let Err(e) = result {
+			warn!("failed to restore the geometry of {}: {}", saved.label, e);
+		}
+	}
+}
+
+fn offer(app: &AppHandle, windows: Vec<SessionWindow>) {
+	let tab_count: usize = windows.iter().map(|w| w.tabs.len()).sum();
+	let message = format!(
+		"Restore {} browser window(s) with {} tab(s) from the last session?",
+		windows.len(),
+		tab_count
+	);
+	let handle = app.clone();
+	app.dialog()
+		.message(message)
+		.title("Restore browser session")
+		.kind(MessageDialogKind::Info)
+		.buttons(MessageDialogButtons::OkCancelCustom("Restore".into(), "Discard".into()))
+		.show(move |accepted| {
+			if !accepted {
+				PREVIOUS.lock().unwrap().clear();
+				return;
+			}
+			// skip what was restored through `restore_browser_session` meanwhile
+			let pending: HashSet<String> = PREVIOUS.lock().unwrap().iter().map(|w| w.label.clone()).collect();
+			let windows = windows.into_iter().filter(|w| pending.contains(&w.label)).collect();
+			tauri::async_runtime::spawn(async move {
+				restore(&handle, windows);
+			});
+		});
+}
+
+// Called once from `setup`.
+pub(crate) fn init(app: &AppHandle) {
+	// frozen once the main window is actually gone, not on a close request that may
+	// still be cancelled; browser windows closed after it no longer change the session
+	if let Some(main) = app.get_window(scope::MAIN_WINDOW) {
+		let handle = app.clone();
+		main.on_window_event(move |event| {
+			if let WindowEvent::Destroyed = event {
+				freeze(&handle);
+			}
+		});
+	}
+	let previous: Vec<SessionWindow> = store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(SESSION_KEY))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default();
+	*PREVIOUS.lock().unwrap() = previous.clone();
+	LOADED.store(true, Ordering::Relaxed);
+	if previous.is_empty() {
+		return;
+	}
+	let app = app.clone();
+	thread::spawn(move || {
+		let started = Instant::now();
+		loop {
+			let previous = PREVIOUS.lock().unwrap().clone();
+			if previous.is_empty() {
+				return;
+			}
+			let (ready, all_up) = restorable(&app, &previous);
+			if all_up || started.elapsed() >= RESTORE_DEADLINE {
+				if ready.is_empty() {
+					info!("no browser window of the last session can be restored");
+				} else {
+					offer(&app, ready);
+				}
+				return;
+			}
+			thread::sleep(PROBE_INTERVAL);
+		}
+	});
+}
+
+// The part of the last session that has not been restored or discarded yet.
+#[command]
+pub async fn get_browser_session() -> Result<Vec<SessionWindow>, String> {
+	Ok(PREVIOUS.lock().unwrap().clone())
+}
+
+// Restores the given windows of the last session, or all of them.
+#[command]
+pub async fn restore_browser_session(
+	app: AppHandle,
+	windows: Option<Vec<String>>,
+) -> Result<(), String> {
+	let selected: Vec<SessionWindow> = PREVIOUS
+		.lock()
+		.unwrap()
+		.iter()
+		.filter(|w| windows.as_ref().is_none_or(|labels| labels.contains(&w.label)))
+		.cloned()
+		.collect();
+	restore(&app, selected);
+	Ok(())
+}
+
+#[command]
+pub async fn discard_browser_session() -> Result<(), String> {
+	PREVIOUS.lock().unwrap().clear();
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn listen_address_matches_the_proxy() {
+		assert!(listens_on("127.0.0.1:7070", "127.0.0.1", 7070));
+		assert!(listens_on("0.0.0.0:7070", "127.0.0.1", 7070));
+		assert!(listens_on("[::]:7070", "::1", 7070));
+		assert!(listens_on("[::1]:7070", "[::1]", 7070));
+		assert!(!listens_on("127.0.0.1:7071", "127.0.0.1", 7070));
+		assert!(!listens_on("10.0.0.2:7070", "127.0.0.1", 7070));
+		assert!(!listens_on("7070", "127.0.0.1", 7070));
+	}
+}
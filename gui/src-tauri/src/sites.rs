@@ -0,0 +1,181 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::Webview;
+use tauri::command;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use log::warn;
+
+use crate::store;
+use crate::tabs;
+
+// Per-origin settings of the browser webviews, in the `site-settings` store key:
+// zoom and user agent follow the webview from page to page, the window size is used
+// when a window is opened for the origin. The user agent is set when a webview is
+// created; only WebKitGTK can change it afterwards, elsewhere a webview keeps the one
+// it was created with.
+const SITES_KEY: &str = "site-settings";
+const MIN_ZOOM: f64 = 0.25;
+const MAX_ZOOM: f64 = 5.;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteSettings {
+	// `https://host[:port]`
+	pub origin: String,
+	#[serde(default)]
+	pub zoom: Option<f64>,
+	#[serde(default)]
+	pub user_agent: Option<String>,
+	// logical pixels
+	#[serde(default)]
+	pub width: Option<f64>,
+	#[serde(default)]
+	pub height: Option<f64>,
+}
+
+lazy_static! {
+	// label -> (zoom, user agent) last applied, to skip redundant changes
+	static ref APPLIED: Mutex<HashMap<String, (f64, Option<String>)>> = Mutex::new(HashMap::new());
+}
+
+fn origin_of(url: &str) -> Option<String> {
+	let url = Url::parse(url).ok()?;
+	let origin = url.origin();
+	origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+fn load_sites(app: &AppHandle) -> Vec<SiteSettings> {
+	store::open_store(app)
+		.ok()
+		.and_then(|store| store.get(SITES_KEY))
+		.and_then(|value| serde_json::from_value(value).ok())
+		.unwrap_or_default()
+}
+
+fn save_sites(app: &AppHandle, sites: &[SiteSettings]) -> Result<(), String> {
+	let store = store::open_store(app)?;
+	store.set(SITES_KEY, serde_json::to_value(sites).map_err(|e| e.to_string())?);
+	store::notify_store_change(app, SITES_KEY, "set");
+	Ok(())
+}
+
+pub(crate) fn find_site(app: &AppHandle, url: &str) -> Option<SiteSettings> {
+	let origin = origin_of(url)?;
+	load_sites(app).into_iter().find(|s| s.origin == origin)
+}
+
+// User agent to create a webview for `url` with.
+pub(crate) fn user_agent(app: &AppHandle, label: &str, url: &str) -> Option<String> {
+	let user_agent = find_site(app, url).and_then(|s| s.user_agent);
+	APPLIED.lock().unwrap().insert(label.to_string(), (1., user_agent.clone()));
+	user_agent
+}
+
+// Size of a window opened for `url`, if its site has one.
+pub(crate) fn window_size(app: &AppHandle, url: &str) -> Option<(f64, f64)> {
+	let site = find_site(app, url)?;
+	Some((site.width?, site.height?))
+}
+
+// Applies the zoom and user agent of the site of `url` to `webview`; sites without
+// settings get the defaults back.
+pub(crate) fn apply(webview: &Webview, url: &Url) {
+	let site = find_site(webview.app_handle(), url.as_str()).unwrap_or_default();
+	let zoom = site.zoom.unwrap_or(1.);
+	let label = webview.label().to_string();
+	let previous = APPLIED.lock().unwrap().insert(label.clone(), (zoom, site.user_agent.clone()));
+	let (previous_zoom, previous_agent) = previous.unwrap_or((1., None));
+	if zoom != previous_zoom
+		&& let Err(e) = webview.set_zoom(zoom)
+	{
+		warn!("failed to zoom {}: {}", label, e);
+	}
+	if site.user_agent != previous_agent {
+		set_user_agent(webview, site.user_agent);
+	}
+}
+
+#[cfg(target_os = "linux")]
+fn set_user_agent(webview: &Webview, user_agent: Option<String>) {
+	use webkit2gtk::{SettingsExt, WebViewExt};
+
+	let result = webview.with_webview(move |platform| {
+		if let Some(settings) = WebViewExt::settings(&platform.inner()) {
+			settings.set_user_agent(user_agent.as_deref());
+		}
+	});
+	if let Err(e) = result {
+		warn!("failed to set the user agent of {}: {}", webview.label(), e);
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_user_agent(_webview: &Webview, _user_agent: Option<String>) {}
+
+pub(crate) fn forget(label: &str) {
+	APPLIED.lock().unwrap().remove(label);
+}
+
+// Re-applies settings to the open tabs of `origin`.
+fn refresh(app: &AppHandle, origin: &str) {
+	for tab in tabs::all_windows().into_iter().flat_map(|w| w.tabs) {
+		if origin_of(&tab.url).as_deref() != Some(origin) {
+			continue;
+		}
+		if let (Some(webview), Ok(url)) = (app.get_webview(&tab.label), Url::parse(&tab.url)) {
+			apply(&webview, &url);
+		}
+	}
+}
+
+#[command]
+pub async fn list_site_settings(
+	app: AppHandle,
+) -> Result<Vec<SiteSettings>, String> {
+	Ok(load_sites(&app))
+}
+
+// Adds or replaces the settings of `settings.origin`, which may be given as any URL of the site.
+#[command]
+pub async fn set_site_settings(
+	app: AppHandle,
+	settings: SiteSettings,
+) -> Result<SiteSettings, String> {
+	let origin = origin_of(&settings.origin).ok_or_else(|| format!("Invalid origin {}", settings.origin))?;
+	if let Some(zoom) = settings.zoom
+		&& !(MIN_ZOOM..=MAX_ZOOM).contains(&zoom)
+	{
+		return Err(format!("Zoom must be between {} and {}", MIN_ZOOM, MAX_ZOOM));
+	}
+	if settings.width.is_some_and(|w| w <= 0.) || settings.height.is_some_and(|h| h <= 0.) {
+		return Err("Window size must be positive".to_string());
+	}
+	let settings = SiteSettings {
+		origin: origin.clone(),
+		user_agent: settings.user_agent.filter(|ua| !ua.trim().is_empty()),
+		..settings
+	};
+	let mut sites = load_sites(&app);
+	sites.retain(|s| s.origin != origin);
+	sites.push(settings.clone());
+	save_sites(&app, &sites)?;
+	refresh(&app, &origin);
+	Ok(settings)
+}
+
+#[command]
+pub async fn remove_site_settings(
+	app: AppHandle,
+	origin: String,
+) -> Result<(), String> {
+	let origin = origin_of(&origin).unwrap_or(origin);
+	let mut sites = load_sites(&app);
+	sites.retain(|s| s.origin != origin);
+	save_sites(&app, &sites)?;
+	refresh(&app, &origin);
+	Ok(())
+}
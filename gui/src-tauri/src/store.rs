@@ -1,8 +1,190 @@
+use lazy_static::lazy_static;
 use tauri::AppHandle;
 use tauri::command;
+use tauri::{Emitter, EventTarget, Webview, Wry};
 use tauri_plugin_store::{Store, StoreExt};
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::PathBuf, sync::{Arc, Mutex}, time::Duration};
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use log::{info, warn, error};
+
+use crate::bookmarks;
+use crate::scope;
+
+pub const STORE_FILE: &str = "store.json";
+pub const SCHEMA_VERSION_KEY: &str = "__schema_version";
+
+pub const STORE_CHANGED_EVENT: &str = "store-changed";
+
+lazy_static! {
+	// webview label -> key prefixes it wants `store-changed` events for
+	static ref STORE_SUBSCRIPTIONS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Serialize)]
+pub struct StoreChange {
+	pub key: String,
+	pub op: &'static str,
+}
+
+// Tells every webview subscribed to a prefix of `key` that the key was mutated.
+// Must be called by every command that writes to store.json. An empty key means
+// the whole store was replaced and reaches every subscriber.
+pub(crate) fn notify_store_change(app: &AppHandle, key: &str, op: &'static str) {
+	let targets: Vec<String> = STORE_SUBSCRIPTIONS
+		.lock()
+		.unwrap()
+		.iter()
+		.filter(|(_, prefixes)| key.is_empty() || prefixes.iter().any(|p| key.starts_with(p.as_str())))
+		.map(|(label, _)| label.clone())
+		.collect();
+	let change = StoreChange { key: key.to_string(), op };
+	for label in targets {
+		if let Err(e) = app.emit_to(EventTarget::webview(&label), STORE_CHANGED_EVENT, change.clone()) {
+			warn!("failed to emit {} to {}: {}", STORE_CHANGED_EVENT, label, e);
+		}
+	}
+}
+
+// Drops the subscriptions of webview `label` once it is gone; labels are reused.
+pub(crate) fn forget(label: &str) {
+	STORE_SUBSCRIPTIONS.lock().unwrap().remove(label);
+}
+
+pub(crate) fn open_store(app: &AppHandle) -> Result<Arc<Store<Wry>>, String> {
+	app
+		.store_builder(STORE_FILE)
+		.auto_save(Duration::from_millis(100))
+		.build()
+		.map_err(|e| format!("Failed to open {}: {}", STORE_FILE, e))
+}
+
+pub(crate) fn read_list(store: &Store<Wry>, key: &str) -> Vec<JsonValue> {
+	store
+		.get(key)
+		.and_then(|json| json.as_array().cloned())
+		.unwrap_or_default()
+}
+
+// A migration rewrites the whole key space of store.json from `version - 1`
+// to `version`. It works on a detached copy, so returning an error leaves the
+// persisted store untouched.
+pub struct Migration {
+	pub version: u64,
+	pub name: &'static str,
+	pub up: fn(&mut HashMap<String, JsonValue>) -> Result<(), String>,
+}
+
+// Registry of store.json schema migrations, ordered by version.
+// Append new entries here; never edit or reorder shipped ones.
+const MIGRATIONS: &[Migration] = &[
+	Migration {
+		version: 1,
+		name: "normalise-bookmark-entries",
+		up: migrate_bookmark_entries,
+	},
+	Migration {
+		version: 2,
+		name: "structured-bookmarks",
+		up: bookmarks::migrate_legacy_lists,
+	},
+];
+
+pub fn current_schema_version() -> u64 {
+	MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+// v1: browser bookmarks (`history`, `shortcut`) were written as `{href, icon, name}`
+// by older toolbars and `{href, icon, title}` by newer ones. Keep a single shape and
+// drop entries that have no usable href.
+fn migrate_bookmark_entries(data: &mut HashMap<String, JsonValue>) -> Result<(), String> {
+	for key in ["history", "shortcut"] {
+		let Some(value) = data.get_mut(key) else {
+			continue;
+		};
+		let Some(items) = value.as_array_mut() else {
+			return Err(format!("'{}' is not a list", key));
+		};
+		items.retain(|item| item.get("href").and_then(|h| h.as_str()).is_some_and(|h| !h.is_empty()));
+		for item in items.iter_mut() {
+			let Some(obj) = item.as_object_mut() else {
+				continue;
+			};
+			if !obj.contains_key("title") {
+				let title = obj.remove("name").unwrap_or_else(|| obj["href"].clone());
+				obj.insert("title".to_string(), title);
+			}
+			if !obj.contains_key("icon") {
+				obj.insert("icon".to_string(), JsonValue::String(String::new()));
+			}
+		}
+	}
+	Ok(())
+}
+
+// Runs the migrations `data` has not seen yet, e.g. on the store.json of a backup
+// before it is restored. Returns the version `data` started at.
+pub(crate) fn migrate_entries(data: &mut HashMap<String, JsonValue>) -> Result<u64, String> {
+	let from = data.get(SCHEMA_VERSION_KEY).and_then(|v| v.as_u64()).unwrap_or(0);
+	for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+		info!("store migration {} ({}) start", migration.version, migration.name);
+		if let Err(e) = (migration.up)(data) {
+			error!("store migration {} ({}) failed: {}", migration.version, migration.name, e);
+			return Err(format!("Migration {} ({}) failed: {}", migration.version, migration.name, e));
+		}
+		data.insert(SCHEMA_VERSION_KEY.to_string(), JsonValue::from(migration.version));
+	}
+	Ok(from)
+}
+
+fn stored_schema_version(store: &Store<Wry>) -> u64 {
+	store.get(SCHEMA_VERSION_KEY).and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+fn replace_entries(store: &Store<Wry>, entries: &HashMap<String, JsonValue>) -> Result<(), String> {
+	store.clear();
+	for (key, value) in entries {
+		store.set(key.clone(), value.clone());
+	}
+	store.save().map_err(|e| e.to_string())
+}
+
+fn snapshot_path(app: &AppHandle, version: u64) -> Result<PathBuf, String> {
+	tauri_plugin_store::resolve_store_path(app, format!("{}.v{}.bak", STORE_FILE, version))
+		.map_err(|e| e.to_string())
+}
+
+// Brings store.json up to `current_schema_version()`. Called from `setup`, i.e. before
+// the first webview can invoke a store command. On any failure the store is restored
+// from the snapshot taken beforehand and the schema version is left unchanged, so the
+// migration is retried on the next launch.
+pub fn run_migrations(app: &AppHandle) -> Result<(), String> {
+	let store = open_store(app)?;
+	let from = stored_schema_version(&store);
+	let target = current_schema_version();
+	if from >= target {
+		return Ok(());
+	}
+
+	let snapshot: HashMap<String, JsonValue> = store.entries().into_iter().collect();
+	let store_path = tauri_plugin_store::resolve_store_path(app, STORE_FILE).map_err(|e| e.to_string())?;
+	if store_path.exists() {
+		let backup = snapshot_path(app, from)?;
+		std::fs::copy(&store_path, &backup)
+			.map_err(|e| format!("Failed to snapshot {} to {:?}: {}", STORE_FILE, backup, e))?;
+	}
+
+	let mut working = snapshot.clone();
+	migrate_entries(&mut working)?;
+
+	if let Err(e) = replace_entries(&store, &working) {
+		warn!("store migration commit failed, rolling back: {}", e);
+		replace_entries(&store, &snapshot)?;
+		return Err(e);
+	}
+	info!("store schema migrated from v{} to v{}", from, target);
+	Ok(())
+}
 
 #[command]
 pub async fn push_store_list(
@@ -10,29 +192,11 @@ pub async fn push_store_list(
 	key: String,
 	value: JsonValue,
 ) -> Result<(),()> {
-	unsafe {
-		let store = app
-        .store_builder("store.json")
-        .auto_save(Duration::from_millis(100))
-        .build()
-        .unwrap();
-			
-		let mut list = store
-			.get(&key)
-			.and_then(|json| {
-					json.as_array().map(|array| {
-						array
-								.iter()
-								.cloned()
-								.collect()
-					})
-			})
-			.unwrap_or_else(Vec::new);
-		
-		list.push(value);
-		
-		store.set(&key, serde_json::json!(list));
-	}
+	let store = open_store(&app).map_err(|_| ())?;
+	let mut list = read_list(&store, &key);
+	list.push(value);
+	store.set(&key, serde_json::json!(list));
+	notify_store_change(&app, &key, "push");
 	Ok(())
 }
 
@@ -42,15 +206,9 @@ pub async fn set_store_list(
 	key: String,
 	value: Vec<JsonValue>,
 ) -> Result<(),()> {
-	unsafe {
-		let store = app
-        .store_builder("store.json")
-        .auto_save(Duration::from_millis(100))
-        .build()
-        .unwrap();
-		
-		store.set(&key, serde_json::json!(value));
-	}
+	let store = open_store(&app).map_err(|_| ())?;
+	store.set(&key, serde_json::json!(value));
+	notify_store_change(&app, &key, "set");
 	Ok(())
 }
 
@@ -59,24 +217,229 @@ pub async fn get_store_list(
 	app: tauri::AppHandle,
 	key: String,
 ) -> Result<Vec<JsonValue>,()> {
-	let mut list = Vec::new();
-	let store = app
-			.store_builder("store.json")
-			.auto_save(Duration::from_millis(100))
-			.build()
-			.unwrap();
-	
-	list = store
-		.get(&key)
-		.and_then(|json| {
-				json.as_array().map(|array| {
-					array
-							.iter()
-							.cloned()
-							.collect()
-				})
+	let store = open_store(&app).map_err(|_| ())?;
+	Ok(read_list(&store, &key))
+}
+
+// Filter and window for reads over large lists such as `bot-history-*`.
+// `contains` is matched case-insensitively against `field` (a dot-separated path
+// into each item) or, without a field, against the item's JSON text.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreListQuery {
+	pub offset: Option<usize>,
+	pub limit: Option<usize>,
+	pub newest_first: Option<bool>,
+	pub field: Option<String>,
+	pub contains: Option<String>,
+	pub equals: Option<JsonValue>,
+}
+
+#[derive(Serialize)]
+pub struct StoreListItem {
+	// position in the stored list, usable with `set_store_list` edits
+	pub index: usize,
+	pub value: JsonValue,
+}
+
+#[derive(Serialize)]
+pub struct StoreListPage {
+	pub total: usize,
+	pub items: Vec<StoreListItem>,
+}
+
+fn lookup_field<'a>(item: &'a JsonValue, field: &str) -> Option<&'a JsonValue> {
+	field.split('.').try_fold(item, |value, part| match value {
+		JsonValue::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+		_ => value.get(part),
+	})
+}
+
+fn matches_query(item: &JsonValue, query: &StoreListQuery) -> bool {
+	let target = match &query.field {
+		Some(field) => match lookup_field(item, field) {
+			Some(value) => value,
+			None => return false,
+		},
+		None => item,
+	};
+	if query.equals.as_ref().is_some_and(|equals| target != equals) {
+		return false;
+	}
+	if let Some(needle) = query.contains.as_ref().filter(|n| !n.is_empty()) {
+		let haystack = match target {
+			JsonValue::String(text) => text.to_lowercase(),
+			other => other.to_string().to_lowercase(),
+		};
+		if !haystack.contains(&needle.to_lowercase()) {
+			return false;
+		}
+	}
+	true
+}
+
+fn filtered_indices(list: &[JsonValue], query: &StoreListQuery) -> Vec<usize> {
+	let mut indices: Vec<usize> = (0..list.len()).filter(|&i| matches_query(&list[i], query)).collect();
+	if query.newest_first.unwrap_or(true) {
+		indices.reverse();
+	}
+	indices
+}
+
+// Returns one page of a stored list, newest (last pushed) first unless
+// `newestFirst: false` is given, together with the number of matching items.
+#[command]
+pub async fn query_store_list(
+	app: tauri::AppHandle,
+	key: String,
+	query: Option<StoreListQuery>,
+) -> Result<StoreListPage, String> {
+	let query = query.unwrap_or_default();
+	let store = open_store(&app)?;
+	let mut list = read_list(&store, &key);
+	let indices = filtered_indices(&list, &query);
+	let items = indices
+		.iter()
+		.skip(query.offset.unwrap_or(0))
+		.take(query.limit.unwrap_or(usize::MAX))
+		.map(|&index| StoreListItem {
+			index,
+			value: std::mem::take(&mut list[index]),
 		})
-		.unwrap_or_else(Vec::new);
-		
-	Ok(list)
-}
\ No newline at end of file
+		.collect();
+	Ok(StoreListPage { total: indices.len(), items })
+}
+
+#[command]
+pub async fn count_store_list(
+	app: tauri::AppHandle,
+	key: String,
+	query: Option<StoreListQuery>,
+) -> Result<usize, String> {
+	let store = open_store(&app)?;
+	let list = read_list(&store, &key);
+	Ok(match query {
+		Some(query) => list.iter().filter(|item| matches_query(item, &query)).count(),
+		None => list.len(),
+	})
+}
+
+// Subscribes the calling webview to `store-changed` events for keys starting with
+// `prefix`. An empty prefix matches every key.
+#[command]
+pub fn subscribe_store_changes(
+	webview: Webview,
+	prefix: String,
+) -> Result<(), String> {
+	if scope::is_browser_webview(webview.label()) && !scope::BROWSER_STORE_PREFIXES.iter().any(|p| prefix.starts_with(p)) {
+		return Err(format!("Store key {} is not available to browser pages", prefix));
+	}
+	let mut subscriptions = STORE_SUBSCRIPTIONS.lock().unwrap();
+	let prefixes = subscriptions.entry(webview.label().to_string()).or_default();
+	if !prefixes.contains(&prefix) {
+		prefixes.push(prefix);
+	}
+	Ok(())
+}
+
+#[command]
+pub fn unsubscribe_store_changes(
+	webview: Webview,
+	prefix: Option<String>,
+) -> Result<(), String> {
+	let mut subscriptions = STORE_SUBSCRIPTIONS.lock().unwrap();
+	match prefix {
+		Some(prefix) => {
+			if let Some(prefixes) = subscriptions.get_mut(webview.label()) {
+				prefixes.retain(|p| p != &prefix);
+			}
+		}
+		None => {
+			subscriptions.remove(webview.label());
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn migrations_are_ordered() {
+		for (i, migration) in MIGRATIONS.iter().enumerate() {
+			assert_eq!(migration.version, i as u64 + 1);
+		}
+		assert_eq!(current_schema_version(), MIGRATIONS.len() as u64);
+	}
+
+	#[test]
+	fn v1_normalises_bookmark_entries() {
+		let mut data = HashMap::from([(
+			"history".to_string(),
+			json!([
+				{"href": "http://a.example/", "name": "A"},
+				{"href": "http://b.example/", "icon": "b.png", "title": "B"},
+				{"href": "", "title": "empty"},
+				{"title": "no href"},
+			]),
+		)]);
+		migrate_bookmark_entries(&mut data).unwrap();
+		assert_eq!(data["history"], json!([
+			{"href": "http://a.example/", "title": "A", "icon": ""},
+			{"href": "http://b.example/", "icon": "b.png", "title": "B"},
+		]));
+	}
+
+	#[test]
+	fn v1_rejects_a_non_list() {
+		let mut data = HashMap::from([("shortcut".to_string(), json!({"href": "x"}))]);
+		assert!(migrate_bookmark_entries(&mut data).is_err());
+	}
+
+	#[test]
+	fn legacy_lists_become_bookmarks() {
+		let mut data = HashMap::from([
+			("history".to_string(), json!([{"href": "http://a.example/", "name": "A"}])),
+			("shortcut".to_string(), json!([{"href": "http://s.example/", "title": "S", "icon": "s.png"}])),
+			("other".to_string(), json!(1)),
+		]);
+		assert_eq!(migrate_entries(&mut data).unwrap(), 0);
+		assert_eq!(data[SCHEMA_VERSION_KEY], json!(current_schema_version()));
+		assert!(!data.contains_key("history") && !data.contains_key("shortcut"));
+		assert_eq!(data["other"], json!(1));
+		let tree: bookmarks::BookmarkTree = serde_json::from_value(data[bookmarks::BOOKMARKS_KEY].clone()).unwrap();
+		assert!(tree.folders.iter().any(|f| f.id == bookmarks::SHORTCUTS_FOLDER));
+		let found: Vec<(&str, &str, Option<&str>)> = tree
+			.bookmarks
+			.iter()
+			.map(|b| (b.url.as_str(), b.title.as_str(), b.folder.as_deref()))
+			.collect();
+		assert_eq!(found, vec![
+			("http://a.example/", "A", None),
+			("http://s.example/", "S", Some(bookmarks::SHORTCUTS_FOLDER)),
+		]);
+	}
+
+	#[test]
+	fn migrating_twice_changes_nothing() {
+		let mut data = HashMap::from([("history".to_string(), json!([{"href": "http://a.example/"}]))]);
+		migrate_entries(&mut data).unwrap();
+		let once = data.clone();
+		assert_eq!(migrate_entries(&mut data).unwrap(), current_schema_version());
+		assert_eq!(data, once);
+	}
+
+	#[test]
+	fn migrations_resume_from_the_stored_version() {
+		// a v1 store whose lists were already normalised keeps entries v1 would drop
+		let mut data = HashMap::from([
+			(SCHEMA_VERSION_KEY.to_string(), json!(1)),
+			("history".to_string(), json!([{"href": "http://a.example/", "title": "A", "icon": ""}])),
+		]);
+		assert_eq!(migrate_entries(&mut data).unwrap(), 1);
+		let tree: bookmarks::BookmarkTree = serde_json::from_value(data[bookmarks::BOOKMARKS_KEY].clone()).unwrap();
+		assert_eq!(tree.bookmarks.len(), 1);
+	}
+}
@@ -0,0 +1,406 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::{Emitter, Webview, Window, WindowEvent};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::Serialize;
+use url::Url;
+use log::warn;
+
+use crate::browser;
+use crate::downloads;
+use crate::handoff;
+use crate::history;
+use crate::navigation;
+use crate::scope;
+use crate::session;
+use crate::sites;
+use crate::store;
+use crate::telemetry;
+
+// A browser window holds one child webview per tab; only the active one is shown.
+// Tabs are keyed by webview label, windows by window label.
+pub const TAB_STATE_EVENT: &str = "tab-state";
+// Window that tabs opened from an app window go to.
+const BROWSER_WINDOW: &str = "browser";
+
+#[derive(Clone, Serialize, Default)]
+pub struct Tab {
+	pub label: String,
+	pub window: String,
+	pub url: String,
+	pub title: String,
+	pub loading: bool,
+	pub proxy: String,
+	pub mesh: String,
+	pub incognito: bool,
+}
+
+#[derive(Clone, Serialize, Default)]
+pub struct TabWindow {
+	pub window: String,
+	pub active: Option<String>,
+	pub tabs: Vec<Tab>,
+	// private window: every tab uses a non-persistent data store and none of them
+	// ends up in the history or the saved session
+	pub incognito: bool,
+}
+
+lazy_static! {
+	static ref TABS: Mutex<HashMap<String, TabWindow>> = Mutex::new(HashMap::new());
+}
+static NEXT_TAB: AtomicU64 = AtomicU64::new(1);
+
+fn window_of(label: &str) -> Option<String> {
+	TABS
+		.lock()
+		.unwrap()
+		.values()
+		.find(|w| w.tabs.iter().any(|t| t.label == label))
+		.map(|w| w.window.clone())
+}
+
+fn snapshot(window: &str) -> Option<TabWindow> {
+	TABS.lock().unwrap().get(window).cloned()
+}
+
+pub(crate) fn find_tab(label: &str) -> Option<Tab> {
+	TABS
+		.lock()
+		.unwrap()
+		.values()
+		.flat_map(|w| w.tabs.iter())
+		.find(|t| t.label == label)
+		.cloned()
+}
+
+// Whether webview `label` belongs to a private window. Its first page loads before
+// it is registered, so the `private_` prefix of `create_proxy_webview` counts too.
+pub(crate) fn is_private(label: &str) -> bool {
+	label.starts_with("private_") || find_tab(label).is_some_and(|t| t.incognito)
+}
+
+// What the log may show of `text`, a URL or host seen in webview `label`: nothing
+// for private tabs.
+pub(crate) fn loggable(label: &str, text: &str) -> String {
+	if is_private(label) { "(private)".to_string() } else { text.to_string() }
+}
+
+fn emit_state(app: &AppHandle, window: &str) {
+	let state = snapshot(window).unwrap_or_else(|| TabWindow {
+		window: window.to_string(),
+		..Default::default()
+	});
+	// what private windows show is never sent anywhere
+	if state.incognito {
+		return;
+	}
+	if let Err(e) = app.emit_to(scope::MAIN_WINDOW, TAB_STATE_EVENT, state) {
+		warn!("failed to emit {}: {}", TAB_STATE_EVENT, e);
+	}
+	session::touch(app);
+}
+
+// Records a webview that was added to `window` as a tab and makes it active.
+pub(crate) fn register_tab(app: &AppHandle, window: &str, label: &str, url: &str, proxy: &str, mesh: &str, incognito: bool) {
+	{
+		let mut tabs = TABS.lock().unwrap();
+		let entry = tabs.entry(window.to_string()).or_insert_with(|| TabWindow {
+			window: window.to_string(),
+			incognito,
+			..Default::default()
+		});
+		if !entry.tabs.iter().any(|t| t.label == label) {
+			entry.tabs.push(Tab {
+				label: label.to_string(),
+				window: window.to_string(),
+				url: url.to_string(),
+				title: String::new(),
+				loading: true,
+				proxy: proxy.to_string(),
+				mesh: mesh.to_string(),
+				incognito: entry.incognito,
+			});
+		}
+	}
+	show_only(app, window, label);
+}
+
+// Applies `f` to the tab behind webview `label`, if it is one, and publishes the new state.
+pub(crate) fn update_tab(app: &AppHandle, label: &str, f: impl FnOnce(&mut Tab)) {
+	let window = {
+		let mut tabs = TABS.lock().unwrap();
+		let Some(tab) = tabs.values_mut().flat_map(|w| w.tabs.iter_mut()).find(|t| t.label == label) else {
+			return;
+		};
+		f(tab);
+		tab.window.clone()
+	};
+	emit_state(app, &window);
+}
+
+// Drops the registry entry once the OS window is gone; moves and resizes only
+// concern the saved session.
+pub(crate) fn watch_window(window: &Window) {
+	let app = window.app_handle().clone();
+	let label = window.label().to_string();
+	window.on_window_event(move |event| match event {
+		WindowEvent::Destroyed => {
+			let removed = TABS.lock().unwrap().remove(&label);
+			for tab in removed.iter().flat_map(|w| w.tabs.iter()) {
+				sites::forget(&tab.label);
+				telemetry::forget(&tab.label);
+				downloads::forget(&tab.label);
+				history::forget(&tab.label);
+				navigation::forget(&tab.label);
+				handoff::tab_closed(&tab.label);
+				store::forget(&tab.label);
+			}
+			store::forget(&label);
+			emit_state(&app, &label);
+		}
+		WindowEvent::Resized(_) | WindowEvent::Moved(_) => session::touch(&app),
+		_ => {}
+	});
+}
+
+fn show_only(app: &AppHandle, window: &str, label: &str) {
+	let labels: Vec<String> = {
+		let mut tabs = TABS.lock().unwrap();
+		let Some(entry) = tabs.get_mut(window) else {
+			return;
+		};
+		entry.active = Some(label.to_string());
+		entry.tabs.iter().map(|t| t.label.clone()).collect()
+	};
+	for other in labels {
+		if let Some(webview) = app.get_webview(&other) {
+			let result = if other == label {
+				webview.show().and_then(|_| webview.set_focus())
+			} else {
+				webview.hide()
+			};
+			if let Err(e) = result {
+				warn!("failed to switch tab {}: {}", other, e);
+			}
+		}
+	}
+	emit_state(app, window);
+}
+
+pub(crate) fn all_windows() -> Vec<TabWindow> {
+	TABS.lock().unwrap().values().cloned().collect()
+}
+
+pub(crate) fn all_tabs() -> Vec<Tab> {
+	TABS.lock().unwrap().values().flat_map(|w| w.tabs.iter().cloned()).collect()
+}
+
+// Proxy used by any open tab of `mesh`, for navigations that must leave a direct tab.
+pub(crate) fn mesh_proxy(mesh: &str) -> Option<String> {
+	TABS
+		.lock()
+		.unwrap()
+		.values()
+		.flat_map(|w| w.tabs.iter())
+		.find(|t| t.mesh == mesh && !t.proxy.is_empty())
+		.map(|t| t.proxy.clone())
+}
+
+pub(crate) fn open_tab_in(
+	app: &AppHandle,
+	window_label: &str,
+	url: &str,
+	proxy: &str,
+	mesh: &str,
+	background: bool,
+	from_page: bool,
+) -> Result<Tab, String> {
+	if scope::is_app_window(window_label) {
+		return Err(format!("Window {} cannot hold browser tabs", window_label));
+	}
+	let label = format!("{}_tab_{}", window_label, NEXT_TAB.fetch_add(1, Ordering::Relaxed));
+	// tabs opened by the user skip the navigation policy for their first page,
+	// tabs opened by a page (`_blank` links, `window.open`) do not
+	if !from_page {
+		navigation::approve(&label, url);
+	}
+	let window = match app.get_window(window_label) {
+		Some(window) => window,
+		None => {
+			let (width, height) = sites::window_size(app, url).unwrap_or((1280., 860.));
+			let window = tauri::window::WindowBuilder::new(app, window_label)
+				.inner_size(width, height)
+				.title(window_label)
+				.build()
+				.map_err(|e| e.to_string())?;
+			watch_window(&window);
+			window
+		}
+	};
+	// tabs of a private window are private too
+	let incognito = snapshot(window_label).is_some_and(|w| w.incognito);
+	let builder = browser::browser_webview_builder(app, &label, url, proxy, mesh, true, incognito)
+		.map_err(|e| e.to_string())?;
+	let webview = window
+		.add_child(builder, tauri::LogicalPosition::new(0, 0), window.inner_size().map_err(|e| e.to_string())?)
+		.map_err(|e| e.to_string())?;
+	browser::attach_browser_webview(app, &webview, url);
+
+	let previous = snapshot(window_label).and_then(|w| w.active);
+	register_tab(app, window_label, &label, url, proxy, mesh, incognito);
+	if let Some(previous) = previous.filter(|_| background) {
+		show_only(app, window_label, &previous);
+	}
+	find_tab(&label).ok_or_else(|| format!("Tab {} was not registered", label))
+}
+
+// Serves a navigation that the routing rules moved out of webview `from` in a tab of
+// the same window using `proxy` (empty for direct), reusing one when it exists.
+pub(crate) fn serve_in_context(app: &AppHandle, from: &str, url: &Url, proxy: &str) {
+	let app = app.clone();
+	let from = from.to_string();
+	let url = url.clone();
+	let proxy = proxy.to_string();
+	tauri::async_runtime::spawn(async move {
+		let source = find_tab(&from);
+		let window = match (&source, app.get_webview(&from)) {
+			(Some(tab), _) => tab.window.clone(),
+			(None, Some(webview)) => webview.window().label().to_string(),
+			(None, None) => return,
+		};
+		let mesh = source.map(|t| t.mesh).unwrap_or_default();
+		let existing = snapshot(&window)
+			.and_then(|w| w.tabs.into_iter().find(|t| t.proxy == proxy && t.mesh == mesh));
+		let result = match existing.and_then(|t| app.get_webview(&t.label)) {
+			Some(webview) => {
+				// already let through by the policy of `from`
+				navigation::approve(webview.label(), url.as_str());
+				webview
+					.navigate(url.clone())
+					.map(|_| show_only(&app, &window, webview.label()))
+					.map_err(|e| e.to_string())
+			}
+			None => open_tab_in(&app, &window, url.as_str(), &proxy, &mesh, false, false).map(|_| ()),
+		};
+		if let Err(e) = result {
+			warn!("failed to route {} out of {}: {}", loggable(&from, url.as_str()), from, e);
+		}
+	});
+}
+
+// Opens `url` as a new tab. Without `window` the tab goes next to the calling
+// webview, or into the `browser` window when called from an app window; without
+// `proxy`/`mesh` it inherits the caller's.
+#[command]
+pub async fn open_tab(
+	app: AppHandle,
+	webview: Webview,
+	url: String,
+	window: Option<String>,
+	proxy: Option<String>,
+	mesh: Option<String>,
+	background: Option<bool>,
+) -> Result<Tab, String> {
+	let caller = find_tab(webview.label());
+	// a page may only open tabs next to itself, on its own proxy and mesh
+	let from_page = scope::is_browser_webview(webview.label());
+	let (window, proxy, mesh) = if from_page {
+		(None, None, None)
+	} else {
+		(window, proxy, mesh)
+	};
+	let window_label = window.unwrap_or_else(|| {
+		let own = webview.window().label().to_string();
+		if scope::is_app_window(&own) { BROWSER_WINDOW.to_string() } else { own }
+	});
+	let proxy = proxy
+		.or_else(|| caller.as_ref().map(|t| t.proxy.clone()))
+		.unwrap_or_default();
+	let mesh = mesh
+		.or_else(|| caller.as_ref().map(|t| t.mesh.clone()))
+		.unwrap_or_default();
+	open_tab_in(&app, &window_label, &url, &proxy, &mesh, background.unwrap_or(false), from_page)
+}
+
+#[command]
+pub async fn close_tab(
+	app: AppHandle,
+	label: String,
+) -> Result<(), String> {
+	let window = window_of(&label).ok_or_else(|| format!("No tab {}", label))?;
+	let next = {
+		let mut tabs = TABS.lock().unwrap();
+		let entry = tabs.get_mut(&window).ok_or_else(|| format!("No tab window {}", window))?;
+		let index = entry.tabs.iter().position(|t| t.label == label).unwrap_or(0);
+		entry.tabs.retain(|t| t.label != label);
+		if entry.active.as_deref() == Some(label.as_str()) {
+			entry.active = None;
+		}
+		entry.active.clone().or_else(|| {
+			entry.tabs.get(index).or_else(|| entry.tabs.last()).map(|t| t.label.clone())
+		})
+	};
+	if let Some(webview) = app.get_webview(&label) {
+		webview.close().map_err(|e| e.to_string())?;
+	}
+	sites::forget(&label);
+	telemetry::forget(&label);
+	downloads::forget(&label);
+	history::forget(&label);
+	navigation::forget(&label);
+	handoff::tab_closed(&label);
+	store::forget(&label);
+	match next {
+		Some(next) => show_only(&app, &window, &next),
+		None => {
+			// last tab closed: the window goes with it
+			if let Some(window) = app.get_window(&window) {
+				window.close().map_err(|e| e.to_string())?;
+			}
+		}
+	}
+	Ok(())
+}
+
+#[command]
+pub async fn activate_tab(
+	app: AppHandle,
+	label: String,
+) -> Result<(), String> {
+	let window = window_of(&label).ok_or_else(|| format!("No tab {}", label))?;
+	show_only(&app, &window, &label);
+	Ok(())
+}
+
+#[command]
+pub async fn reorder_tab(
+	app: AppHandle,
+	label: String,
+	index: usize,
+) -> Result<(), String> {
+	let window = window_of(&label).ok_or_else(|| format!("No tab {}", label))?;
+	{
+		let mut tabs = TABS.lock().unwrap();
+		let entry = tabs.get_mut(&window).ok_or_else(|| format!("No tab window {}", window))?;
+		let from = entry.tabs.iter().position(|t| t.label == label).ok_or_else(|| format!("No tab {}", label))?;
+		let tab = entry.tabs.remove(from);
+		let to = index.min(entry.tabs.len());
+		entry.tabs.insert(to, tab);
+	}
+	emit_state(&app, &window);
+	Ok(())
+}
+
+// Lists the tabs of one window, or of every browser window when `window` is omitted.
+#[command]
+pub async fn list_tabs(window: Option<String>) -> Result<Vec<TabWindow>, String> {
+	let tabs = TABS.lock().unwrap();
+	Ok(match window {
+		Some(window) => tabs.get(&window).cloned().into_iter().collect(),
+		None => tabs.values().cloned().collect(),
+	})
+}
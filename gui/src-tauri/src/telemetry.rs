@@ -0,0 +1,162 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Emitter;
+use tauri::Webview;
+use tauri::command;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use log::warn;
+
+use crate::scope;
+use crate::tabs;
+
+// Page loads of the browser webviews, emitted as `page-load` when they start, finish
+// or fail and kept in a rolling log per webview until it closes. Only WebKitGTK
+// reports failed loads; elsewhere a failed load shows up as finished.
+pub const PAGE_LOAD_EVENT: &str = "page-load";
+// entries kept per webview
+const LOG_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadPhase {
+	Started,
+	Finished,
+	Failed,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageLoad {
+	pub label: String,
+	pub window: String,
+	pub phase: LoadPhase,
+	pub url: String,
+	pub title: String,
+	// empty for direct
+	pub proxy: String,
+	pub mesh: String,
+	// since the load started, for finished and failed loads
+	pub elapsed_ms: Option<u64>,
+	pub error: Option<String>,
+	// unix milliseconds
+	pub at: u64,
+}
+
+lazy_static! {
+	static ref LOGS: Mutex<HashMap<String, VecDeque<PageLoad>>> = Mutex::new(HashMap::new());
+	// label -> start of the load in progress
+	static ref STARTED: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+fn now_ms() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn record(app: &AppHandle, label: &str, phase: LoadPhase, url: &str, error: Option<String>) {
+	// private tabs leave no trace
+	if tabs::is_private(label) {
+		return;
+	}
+	let elapsed_ms = match phase {
+		LoadPhase::Started => {
+			STARTED.lock().unwrap().insert(label.to_string(), Instant::now());
+			None
+		}
+		// a failed load still finishes, only the first end counts
+		_ => match STARTED.lock().unwrap().remove(label) {
+			Some(started) => Some(started.elapsed().as_millis() as u64),
+			None => return,
+		},
+	};
+	let tab = tabs::find_tab(label).unwrap_or_default();
+	let load = PageLoad {
+		label: label.to_string(),
+		window: tab.window,
+		phase,
+		url: url.to_string(),
+		title: tab.title,
+		proxy: tab.proxy,
+		mesh: tab.mesh,
+		elapsed_ms,
+		error,
+		at: now_ms(),
+	};
+	{
+		let mut logs = LOGS.lock().unwrap();
+		let log = logs.entry(label.to_string()).or_default();
+		if log.len() == LOG_LIMIT {
+			log.pop_front();
+		}
+		log.push_back(load.clone());
+	}
+	if let Err(e) = app.emit_to(scope::MAIN_WINDOW, PAGE_LOAD_EVENT, load) {
+		warn!("failed to emit {}: {}", PAGE_LOAD_EVENT, e);
+	}
+}
+
+pub(crate) fn started(app: &AppHandle, label: &str, url: &str) {
+	record(app, label, LoadPhase::Started, url, None);
+}
+
+pub(crate) fn finished(app: &AppHandle, label: &str, url: &str) {
+	record(app, label, LoadPhase::Finished, url, None);
+}
+
+// Called for every browser webview once it is created.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch(app: &AppHandle, webview: &Webview) {
+	use webkit2gtk::WebViewExt;
+
+	let app = app.clone();
+	let label = webview.label().to_string();
+	let result = webview.with_webview(move |platform| {
+		platform.inner().connect_load_failed(move |_, _, failing_uri, error| {
+			record(&app, &label, LoadPhase::Failed, failing_uri, Some(error.to_string()));
+			// keep the default error page
+			false
+		});
+	});
+	if let Err(e) = result {
+		warn!("failed to watch the page loads of {}: {}", webview.label(), e);
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn watch(_app: &AppHandle, _webview: &Webview) {}
+
+pub(crate) fn forget(label: &str) {
+	LOGS.lock().unwrap().remove(label);
+	STARTED.lock().unwrap().remove(label);
+}
+
+// The log of webview `label`, or of all of them, oldest first.
+#[command]
+pub async fn list_page_loads(
+	label: Option<String>,
+) -> Result<Vec<PageLoad>, String> {
+	let logs = LOGS.lock().unwrap();
+	let mut loads: Vec<PageLoad> = logs
+		.iter()
+		.filter(|(l, _)| label.as_ref().is_none_or(|label| label == *l))
+		.flat_map(|(_, log)| log.iter().cloned())
+		.collect();
+	loads.sort_by_key(|l| l.at);
+	Ok(loads)
+}
+
+#[command]
+pub async fn clear_page_loads(
+	label: Option<String>,
+) -> Result<(), String> {
+	let mut logs = LOGS.lock().unwrap();
+	match label {
+		Some(label) => {
+			logs.remove(&label);
+		}
+		None => logs.clear(),
+	}
+	Ok(())
+}
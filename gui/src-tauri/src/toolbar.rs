@@ -0,0 +1,242 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::command;
+use tauri::Webview;
+use tauri::webview::PageLoadPayload;
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use url::Url;
+use log::{info, warn};
+
+use crate::bookmarks::{self, Bookmark, NewBookmark};
+use crate::history;
+use crate::navigation;
+use crate::tabs;
+
+// The browser toolbar ships as `assets/toolbar.js` and is installed as an
+// initialization script, so every page of a browser webview gets it exactly once.
+// The script guards itself with a version number and only calls the commands below.
+pub const TOOLBAR_SCRIPT: &str = include_str!("../assets/toolbar.js");
+
+// Mobile has no separate browser webview: a toolbar page is loaded into `main`,
+// which was built without the script and gets it after each remote page load.
+static MAIN_TOOLBAR: AtomicBool = AtomicBool::new(false);
+
+// The history menu is native, so the page the toolbar runs in never sees the visits
+// and cannot pick an entry or confirm clearing by itself.
+const HISTORY_MENU_SIZE: i64 = 20;
+const HISTORY_ITEM_PREFIX: &str = "ztm-history-";
+const HISTORY_TITLE_CHARS: usize = 60;
+
+enum HistoryItem {
+	Open { label: String, url: String },
+	Clear { mesh: String },
+}
+
+lazy_static! {
+	// menu item id -> action, for the history menu shown last
+	static ref HISTORY_ITEMS: Mutex<HashMap<String, HistoryItem>> = Mutex::new(HashMap::new());
+}
+static HISTORY_MENU_HANDLER: Once = Once::new();
+
+#[cfg(any(target_os = "ios", target_os = "android"))]
+pub(crate) fn enable_main_toolbar() {
+	MAIN_TOOLBAR.store(true, Ordering::Relaxed);
+}
+
+// App-wide `on_page_load` hook, see `enable_main_toolbar`.
+pub fn on_page_load(webview: &Webview, payload: &PageLoadPayload<'_>) {
+	if webview.label() != "main" || !MAIN_TOOLBAR.load(Ordering::Relaxed) {
+		return;
+	}
+	if payload.event() != tauri::webview::PageLoadEvent::Finished {
+		return;
+	}
+	let url = payload.url();
+	if (url.scheme() != "http" && url.scheme() != "https") || url.host_str() == Some("tauri.localhost") {
+		// back in the app itself
+		MAIN_TOOLBAR.store(false, Ordering::Relaxed);
+		return;
+	}
+	if let Err(e) = webview.eval(TOOLBAR_SCRIPT) {
+		warn!("failed to inject toolbar into {}: {}", url, e);
+	}
+}
+
+// What the address bar accepts: full URLs, or `host[:port][/path]` taken as http.
+fn address_to_url(address: &str) -> Result<Url, String> {
+	let address = address.trim();
+	let parsed = if address.contains("://") {
+		Url::parse(address)
+	} else {
+		Url::parse(&format!("http://{}", address))
+	};
+	parsed.map_err(|e| format!("Invalid address {}: {}", address, e))
+}
+
+#[command]
+pub async fn toolbar_navigate(
+	app: AppHandle,
+	webview: Webview,
+	url: String,
+	new_tab: Option<bool>,
+) -> Result<(), String> {
+	let url = address_to_url(&url)?;
+	// the toolbar runs in the page's own context, so page script can call this too:
+	// the navigation policy decides as for any other navigation
+	if !new_tab.unwrap_or(false) {
+		return webview.navigate(url).map_err(|e| e.to_string());
+	}
+	let caller = tabs::find_tab(webview.label());
+	let window = caller
+		.as_ref()
+		.map(|t| t.window.clone())
+		.unwrap_or_else(|| webview.window().label().to_string());
+	let proxy = caller.as_ref().map(|t| t.proxy.clone()).unwrap_or_default();
+	let mesh = caller.map(|t| t.mesh).unwrap_or_default();
+	tabs::open_tab_in(&app, &window, url.as_str(), &proxy, &mesh, false, true).map(|_| ())
+}
+
+#[command]
+pub async fn toolbar_back(webview: Webview) -> Result<(), String> {
+	webview.eval("history.back()").map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn toolbar_forward(webview: Webview) -> Result<(), String> {
+	webview.eval("history.forward()").map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn toolbar_reload(webview: Webview) -> Result<(), String> {
+	webview.reload().map_err(|e| e.to_string())
+}
+
+// Stars (`starred: true`) or un-stars the page in `folder` (root when omitted) of
+// the calling tab's mesh. Returns the bookmark that now exists, if any.
+#[command]
+pub async fn toolbar_bookmark(
+	app: AppHandle,
+	webview: Webview,
+	url: String,
+	title: String,
+	icon: String,
+	folder: Option<String>,
+	starred: bool,
+) -> Result<Option<Bookmark>, String> {
+	if starred {
+		let bookmark = NewBookmark {
+			url,
+			title,
+			icon,
+			folder,
+			tags: vec![],
+			mesh: None,
+		};
+		return bookmarks::add_for(&app, &webview, bookmark).map(Some);
+	}
+	if let Some(existing) = bookmarks::find_for(&app, &webview, &url, None, folder)? {
+		bookmarks::remove(&app, &existing.id)?;
+	}
+	Ok(None)
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+fn menu_text(text: &str) -> String {
+	let mut short: String = text.chars().take(HISTORY_TITLE_CHARS).collect();
+	if short.len() < text.len() {
+		short.push('…');
+	}
+	// `&` marks mnemonics in menu labels
+	short.replace('&', "&&")
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+fn on_history_menu(app: &AppHandle, event: tauri::menu::MenuEvent) {
+	let item = {
+		let mut items = HISTORY_ITEMS.lock().unwrap();
+		let item = items.remove(event.id().as_ref());
+		if item.is_some() {
+			items.clear();
+		}
+		item
+	};
+	match item {
+		Some(HistoryItem::Open { label, url }) => {
+			let (Some(webview), Ok(url)) = (app.get_webview(&label), Url::parse(&url)) else {
+				return;
+			};
+			// picked by the user in the app's own menu
+			navigation::approve(&label, url.as_str());
+			if let Err(e) = webview.navigate(url) {
+				warn!("failed to open a visit in {}: {}", label, e);
+			}
+		}
+		Some(HistoryItem::Clear { mesh }) => {
+			let message = if mesh.is_empty() {
+				"Clear the browsing history?".to_string()
+			} else {
+				format!("Clear the browsing history of mesh {}?", mesh)
+			};
+			app.dialog()
+				.message(message)
+				.title("Clear history")
+				.kind(MessageDialogKind::Warning)
+				.buttons(MessageDialogButtons::OkCancelCustom("Clear".into(), "Cancel".into()))
+				.show(move |accepted| {
+					if !accepted {
+						return;
+					}
+					match history::clear(Some(mesh.clone()), None, None) {
+						Ok(removed) => info!("cleared {} visits of mesh {}", removed, mesh),
+						Err(e) => warn!("failed to clear the history of mesh {}: {}", mesh, e),
+					}
+				});
+		}
+		None => {}
+	}
+}
+
+// Shows the visits of the calling tab's mesh that match `query` in a native menu at
+// the pointer, with an entry to clear them after a native confirmation.
+#[command]
+pub async fn toolbar_history_menu(
+	app: AppHandle,
+	webview: Webview,
+	query: Option<String>,
+) -> Result<(), String> {
+	#[cfg(not(any(target_os = "ios", target_os = "android")))] {
+		use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+
+		let mesh = tabs::find_tab(webview.label()).map(|t| t.mesh).unwrap_or_default();
+		let visits = history::search(Some(mesh.clone()), query, None, None, Some(HISTORY_MENU_SIZE))?;
+		HISTORY_MENU_HANDLER.call_once(|| app.on_menu_event(on_history_menu));
+		let menu = Menu::new(&app).map_err(|e| e.to_string())?;
+		let mut items = HashMap::new();
+		if visits.is_empty() {
+			let empty = MenuItem::new(&app, "No history", false, None::<&str>).map_err(|e| e.to_string())?;
+			menu.append(&empty).map_err(|e| e.to_string())?;
+		}
+		for (i, visit) in visits.into_iter().enumerate() {
+			let id = format!("{}{}", HISTORY_ITEM_PREFIX, i);
+			let title = if visit.title.is_empty() { &visit.url } else { &visit.title };
+			let entry = MenuItem::with_id(&app, &id, menu_text(title), true, None::<&str>).map_err(|e| e.to_string())?;
+			menu.append(&entry).map_err(|e| e.to_string())?;
+			items.insert(id, HistoryItem::Open { label: webview.label().to_string(), url: visit.url });
+		}
+		let clear_id = format!("{}clear", HISTORY_ITEM_PREFIX);
+		let clear = MenuItem::with_id(&app, &clear_id, "Clear history…", true, None::<&str>).map_err(|e| e.to_string())?;
+		menu.append(&PredefinedMenuItem::separator(&app).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+		menu.append(&clear).map_err(|e| e.to_string())?;
+		items.insert(clear_id, HistoryItem::Clear { mesh });
+		*HISTORY_ITEMS.lock().unwrap() = items;
+		webview.window().popup_menu(&menu).map_err(|e| e.to_string())
+	}
+	#[cfg(any(target_os = "ios", target_os = "android"))] {
+		let _ = (app, webview, query);
+		Err("The history menu is not available on this platform".to_string())
+	}
+}
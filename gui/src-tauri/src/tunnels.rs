@@ -0,0 +1,245 @@
+use lazy_static::lazy_static;
+use tauri::AppHandle;
+use tauri::Manager;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{UriSchemeContext, UriSchemeResponder};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use serde_json::Value as JsonValue;
+use url::Url;
+use tauri_plugin_deep_link::DeepLinkExt;
+use log::{info, warn};
+
+use crate::agent;
+use crate::navigation;
+use crate::tabs;
+
+// `ztm://{mesh}/{tunnel}/path?query` opens the service behind a tunnel-app inbound
+// of this agent. The inbound named `{tunnel}` is looked up through the agent and
+// the page redirects to its local listen address; failures render an error page.
+// Windows and Android webviews reach the scheme as `http://ztm.localhost/{mesh}/...`.
+pub const ZTM_SCHEME: &str = "ztm";
+// window deep links are opened in
+const LINK_WINDOW: &str = "tunnels";
+
+lazy_static! {
+	// `ip:port` of the inbounds resolved so far; browsed directly, never via a proxy
+	static ref INBOUNDS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+struct ZtmLink {
+	mesh: String,
+	tunnel: String,
+	// everything after the tunnel name, starting with `/`
+	rest: String,
+}
+
+fn parse_link(url: &Url) -> Option<ZtmLink> {
+	let mut segments: Vec<String> = url
+		.path_segments()
+		.map(|s| s.map(percent_decode).collect())
+		.unwrap_or_default();
+	let mesh = if url.scheme() == ZTM_SCHEME {
+		percent_decode(url.host_str()?)
+	} else {
+		// http://ztm.localhost/{mesh}/...
+		if segments.is_empty() {
+			return None;
+		}
+		segments.remove(0)
+	};
+	if segments.is_empty() || segments[0].is_empty() || mesh.is_empty() {
+		return None;
+	}
+	let tunnel = segments.remove(0);
+	let mut rest = format!("/{}", segments.join("/"));
+	if let Some(query) = url.query() {
+		rest.push('?');
+		rest.push_str(query);
+	}
+	Some(ZtmLink { mesh, tunnel, rest })
+}
+
+fn percent_decode(text: &str) -> String {
+	let mut bytes = Vec::with_capacity(text.len());
+	let mut i = 0;
+	while i < text.len() {
+		let escaped = text.get(i + 1..i + 3).filter(|_| text.as_bytes()[i] == b'%').and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match escaped {
+			Some(byte) => {
+				bytes.push(byte);
+				i += 3;
+			}
+			None => {
+				bytes.push(text.as_bytes()[i]);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+pub(crate) fn is_ztm_url(url: &Url) -> bool {
+	url.scheme() == ZTM_SCHEME || url.host_str() == Some("ztm.localhost")
+}
+
+// The form of a `ztm:` URL the webviews of this platform can load.
+pub(crate) fn platform_url(url: &Url) -> Url {
+	if url.scheme() != ZTM_SCHEME || !cfg!(any(windows, target_os = "android")) {
+		return url.clone();
+	}
+	let mut rewritten = format!("http://ztm.localhost/{}{}", url.host_str().unwrap_or_default(), url.path());
+	if let Some(query) = url.query() {
+		rewritten.push('?');
+		rewritten.push_str(query);
+	}
+	Url::parse(&rewritten).unwrap_or_else(|_| url.clone())
+}
+
+// Whether `url` points at a tunnel inbound resolved through a `ztm:` link.
+pub(crate) fn is_inbound(url: &Url) -> bool {
+	match (url.host_str(), url.port_or_known_default()) {
+		(Some(host), Some(port)) => INBOUNDS.lock().unwrap().contains(&format!("{}:{}", host, port)),
+		_ => false,
+	}
+}
+
+fn listen_host(ip: &str) -> String {
+	match ip {
+		"" | "0.0.0.0" => "127.0.0.1".to_string(),
+		"::" => "[::1]".to_string(),
+		ip if ip.contains(':') => format!("[{}]", ip),
+		ip => ip.to_string(),
+	}
+}
+
+async fn resolve(app: &AppHandle, link: &ZtmLink) -> Result<Url, (StatusCode, String)> {
+	let bad_gateway = |e: String| (StatusCode::BAD_GATEWAY, e);
+	let endpoint = agent::local_endpoint(app, &link.mesh).await.map_err(bad_gateway)?;
+	let inbounds = agent::get_json(app, &["api", "meshes", &link.mesh, "apps", "ztm", "tunnel", "api", "endpoints", &endpoint, "inbound"])
+		.await
+		.map_err(bad_gateway)?;
+	let inbound = inbounds
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter(|i| i.get("name").and_then(JsonValue::as_str) == Some(link.tunnel.as_str()))
+		.min_by_key(|i| i.get("protocol").and_then(JsonValue::as_str) != Some("tcp"))
+		.ok_or_else(|| (StatusCode::NOT_FOUND, format!("There is no tunnel named \"{}\" in mesh \"{}\" on this device.", link.tunnel, link.mesh)))?;
+	let listen = inbound
+		.get("listens")
+		.and_then(JsonValue::as_array)
+		.and_then(|listens| listens.first())
+		.ok_or_else(|| (StatusCode::NOT_FOUND, format!("Tunnel \"{}\" has no local listen port.", link.tunnel)))?;
+	let port = listen.get("port").and_then(JsonValue::as_u64).unwrap_or(0);
+	let host = listen_host(listen.get("ip").and_then(JsonValue::as_str).unwrap_or(""));
+	let target = Url::parse(&format!("http://{}:{}{}", host, port, link.rest)).map_err(|e| bad_gateway(e.to_string()))?;
+	if let (Some(host), Some(port)) = (target.host_str(), target.port_or_known_default()) {
+		INBOUNDS.lock().unwrap().insert(format!("{}:{}", host, port));
+	}
+	Ok(target)
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html(status: StatusCode, body: String) -> Response<Cow<'static, [u8]>> {
+	Response::builder()
+		.status(status)
+		.header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+		.header(header::CACHE_CONTROL, "no-store")
+		.body(Cow::Owned(body.into_bytes()))
+		.unwrap()
+}
+
+fn redirect_page(target: &Url) -> Response<Cow<'static, [u8]>> {
+	let target = escape_html(target.as_str());
+	html(StatusCode::OK, format!(
+		r#"<!DOCTYPE html><html><head><meta charset="utf-8"><meta http-equiv="refresh" content="0;url={0}"><title>Opening tunnel</title></head><body><a href="{0}">{0}</a></body></html>"#,
+		target
+	))
+}
+
+fn error_page(status: StatusCode, link: &str, message: &str) -> Response<Cow<'static, [u8]>> {
+	html(status, format!(
+		r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Tunnel unavailable</title>
+<style>
+	body {{ font-family: "Helvetica Neue", Arial, "Noto Sans", sans-serif; margin: 0; padding: 80px 40px; color: #333; }}
+	h1 {{ font-size: 20px; margin: 0 0 12px; }}
+	p {{ font-size: 14px; line-height: 20px; margin: 0 0 8px; }}
+	code {{ background: #f2f2f2; padding: 2px 6px; border-radius: 4px; word-break: break-all; }}
+	@media (prefers-color-scheme: dark) {{ body {{ background: #18181a; color: #eee; }} code {{ background: #2b2b29; }} }}
+</style></head><body>
+<h1>This tunnel cannot be opened</h1>
+<p>{}</p>
+<p><code>{}</code></p>
+<p>Check that the tunnel app is running in the mesh and that the tunnel has an inbound on this device.</p>
+</body></html>"#,
+		escape_html(message),
+		escape_html(link)
+	))
+}
+
+// Handler registered for `ztm://`.
+pub fn serve_link(ctx: UriSchemeContext<'_, tauri::Wry>, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+	let app = ctx.app_handle().clone();
+	let label = ctx.webview_label().to_string();
+	let uri = request.uri().to_string();
+	tauri::async_runtime::spawn(async move {
+		let link = Url::parse(&uri).ok().and_then(|url| parse_link(&url));
+		let response = match link {
+			None => error_page(StatusCode::BAD_REQUEST, &uri, "Links look like ztm://{mesh}/{tunnel}/path."),
+			Some(link) => match resolve(&app, &link).await {
+				Ok(target) => {
+					// the link itself went through the navigation policy of the webview
+					navigation::approve(&label, target.as_str());
+					redirect_page(&target)
+				}
+				Err((status, message)) => {
+					info!("cannot resolve {}: {}", tabs::loggable(&label, &uri), message);
+					error_page(status, &uri, &message)
+				}
+			},
+		};
+		responder.respond(response);
+	});
+}
+
+// Deep links open in a tab of the `tunnels` window, on the mesh of the link.
+pub(crate) fn open_deep_link(app: &AppHandle, url: &Url) {
+	let Some(link) = parse_link(url) else {
+		warn!("ignoring malformed link {}", url);
+		return;
+	};
+	let target = platform_url(url);
+	if let Err(e) = tabs::open_tab_in(app, LINK_WINDOW, target.as_str(), "", &link.mesh, false, false) {
+		warn!("failed to open {}: {}", url, e);
+		return;
+	}
+	if let Some(window) = app.get_window(LINK_WINDOW)
+		&& let Err(e) = window.set_focus()
+	{
+		warn!("failed to focus {}: {}", LINK_WINDOW, e);
+	}
+}
+
+// Called once from `setup`: handles `ztm://` links given to the app by the system,
+// including the ones it was started with.
+pub(crate) fn init(app: &AppHandle) {
+	let handle = app.clone();
+	app.deep_link().on_open_url(move |event| {
+		for url in event.urls().iter().filter(|url| url.scheme() == ZTM_SCHEME) {
+			open_deep_link(&handle, url);
+		}
+	});
+	match app.deep_link().get_current() {
+		Ok(urls) => {
+			for url in urls.unwrap_or_default().iter().filter(|url| url.scheme() == ZTM_SCHEME) {
+				open_deep_link(app, url);
+			}
+		}
+		Err(e) => warn!("failed to read the launch links: {}", e),
+	}
+}
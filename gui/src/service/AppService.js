@@ -314,17 +314,17 @@ export default class AppService {
 			}); 
 			
 	}
-	openbrowser({mesh, url, width, height, proxy, ep}) {
+	openbrowser({mesh, url, width, height, proxy, ep, incognito}) {
 		if(proxy){
 			this.getProxyListen(mesh).then((res)=>{
 				if(!!res?.listen){
-					this.openWV(url, width, height, res.listen)
+					this.openWV(url, width, height, res.listen, incognito)
 				} else {
-					this.openWV(url, width, height);
+					this.openWV(url, width, height, null, incognito);
 				}
 			});
 		} else {
-			this.openWV(url, width, height)
+			this.openWV(url, width, height, null, incognito)
 		}
 		if(!!ep){
 			this.setProxyOutbound({
@@ -333,13 +333,14 @@ export default class AppService {
 			})
 		}
 	}
-	openWV(url, width, height , listen) {
+	openWV(url, width, height , listen, incognito) {
 		const webviewOptions = {
 			url,
 			name:url.replace(/.*\/\//,"").split("/")[0].replaceAll(".","_").replaceAll("-","_"),
 			width:width||1280,
 			height:height||860,
 			proxy: !!listen?`socks5://${listen}`:'',
+			incognito: !!incognito,
 		}
 		openWebview(webviewOptions, true)
 	}
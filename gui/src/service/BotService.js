@@ -1,7 +1,7 @@
 import { mock, request, getBaseUrl, getUrl,merge,fetchAsStream } from './common/request';
 import toast from "@/utils/toast";
 import confirm from "@/utils/confirm";
-import { getItem, setItem, unshiftItem, STORE_BOT_PROMPT, STORE_SETTING_LLM, STORE_BOT_CONTENT, STORE_BOT_REPLAY } from "@/utils/localStore";
+import { getItem, setItem, unshiftItem, setMessages, STORE_BOT_PROMPT, STORE_SETTING_LLM, STORE_BOT_REPLAY } from "@/utils/localStore";
 import _, { forEach } from 'lodash';
 import store from "@/store";
 import { fetch as tauriFetch } from '@tauri-apps/plugin-http';
@@ -339,7 +339,7 @@ export default class BotService {
 		let url = this.getSvcUrl(`/svc/${llm.kind}/${llm.name}/chat/completions`);
 		writeLogFile('ztm-llm.log', `[${new Date().toISOString()}] request llm ${url} by ${JSON.stringify(body)}\n`);
 		
-		setItem(STORE_BOT_CONTENT(mesh?.name, roomId), messages, ()=> {
+		setMessages(mesh?.name, roomId, 'content', messages, ()=> {
 			this.stream = fetchAsStream();
 			this.stream.post(
 				url, 
@@ -1,4 +1,5 @@
 import { fetch as tauriFetch } from '@tauri-apps/plugin-http';
+import { invoke } from '@tauri-apps/api/core';
 import axios from "axios";
 import Cookie from './cookie'
 import toast from "@/utils/toast";
@@ -185,6 +186,10 @@ const getPort = () => {
 }
 const setPort = (port) => {
 	localStorage.setItem("VITE_APP_API_PORT",port);
+	// ztm:// links are resolved on the Rust side, which needs the agent port too
+	if(!!window.__TAURI_INTERNALS__ && !!Number(port)){
+		invoke('set_agent_port', { port: Number(port) }).catch(() => {});
+	}
 }
 const getHubListen = () => {
 	const VITE_APP_HUB_LISTEN = localStorage.getItem("VITE_APP_HUB_LISTEN");
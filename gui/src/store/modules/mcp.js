@@ -6,8 +6,9 @@ import {
 	deleteItem, 
 	unshiftItem, 
 	pushItem, 
+	getMessages,
+	pushMessage,
 	STORE_SETTING_LLM, 
-	STORE_BOT_CONTENT, 
 	STORE_BOT_ROOMS,
 	STORE_BOT_AGENTS,
 } from "@/utils/localStore";
@@ -101,7 +102,7 @@ export default {
 			const mesh = data?.mesh;
 			const roomId = data?.roomId;
 			let first = true;
-			getItem(STORE_BOT_CONTENT(mesh, roomId), (historyContext)=>{
+			getMessages(mesh, roomId, 'content', (historyContext)=>{
 				commit('setToolcall', null);
 				botService.callRunnerBySDK({
 					...data,
@@ -137,7 +138,7 @@ export default {
 									});
 									//end
 									
-									pushItem(STORE_BOT_CONTENT(mesh, roomId), {
+									pushMessage(mesh, roomId, 'content', {
 										'content': delta, 
 										'refusal': null, 'annotations': null, 'audio': null, 'function_call': null, 
 										'role': 'assistant', 
@@ -156,7 +157,7 @@ export default {
 					}
 				})
 			
-			});
+			},data?.llm?.memoryLength||MAX_CONTENT);
 		},
 		stopAll({ commit, getters }) {
 			const _clients = getters['clients'];
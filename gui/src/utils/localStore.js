@@ -50,6 +50,46 @@ export const deleteItem = (key, index, callback) => {
 	});
 }
 
+// Bot conversations (kind 'history' or 'content') live in the app's message store;
+// outside the app they fall back to the bot-history-*/bot-content-* keys.
+const STORE_BOT_KINDS = {
+	history: (mesh, room) => STORE_BOT_HISTORY(mesh, room),
+	content: (mesh, room) => STORE_BOT_CONTENT(mesh, room),
+}
+
+export const getMessages = (mesh, room, kind, callback, limit) => {
+	if(!!window.__TAURI_INTERNALS__ ){
+		invoke('list_messages',{ mesh, room: room || '', kind, limit }).then((res)=>{
+			callback((res || []).reverse().map((m)=>m.message));
+		});
+	} else {
+		getItem(STORE_BOT_KINDS[kind](mesh, room),(res)=>{
+			callback(!!limit && !!res ? res.slice(-limit) : res);
+		});
+	}
+}
+
+export const setMessages = (mesh, room, kind, value, callback, max) => {
+	if(!!window.__TAURI_INTERNALS__ ){
+		const done = ()=> callback();
+		if(value.length == 0){
+			invoke('clear_messages',{ mesh, room: room || '', kind }).then(done);
+		} else {
+			invoke('sync_messages',{ mesh, room: room || '', kind, messages: value }).then(done);
+		}
+	} else {
+		setItem(STORE_BOT_KINDS[kind](mesh, room), value, callback, max);
+	}
+}
+
+export const pushMessage = (mesh, room, kind, value, callback, max) => {
+	if(!!window.__TAURI_INTERNALS__ ){
+		invoke('append_message',{ mesh, room: room || '', kind, message: value }).then(()=>callback());
+	} else {
+		pushItem(STORE_BOT_KINDS[kind](mesh, room), value, callback, max);
+	}
+}
+
 export const STORE_SETTING_LLM = (mesh, id) => {
 	return `llm-${mesh}`+ (!!id?`-${id}`:'');
 }
@@ -79,6 +79,8 @@ const openWebview = (app, broswer)=>{
 				eval: !!broswer,
 				width: options.width,
 				height:	options.height,
+				mesh: mesh?.name || '',
+				incognito: !!app.incognito,
 			 }
 			 invoke('create_proxy_webview', pluginOption);
 			 